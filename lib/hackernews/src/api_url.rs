@@ -1,38 +1,30 @@
-use std::{fmt::Display, sync::Arc};
+use std::fmt::Display;
 
-use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
-use crate::cache::FirebaseCache;
-
-const API_BASE_URL: &str = "https://hacker-news.firebaseio.com/v0/";
+pub(crate) const API_BASE_URL: &str = "https://hacker-news.firebaseio.com/v0/";
+/// Discussion page of an item, followed by its id.
+pub const HN_ITEM_URL: &str = "https://news.ycombinator.com/item?id=";
 
 const TOP_STORIES_ENDPOINT: &str = "topstories";
 const NEW_STORIES_ENDPOINT: &str = "newstories";
 const SHOW_STORIES_ENDPOINT: &str = "showstories";
 const BEST_STORIES_ENDPOINT: &str = "beststories";
+const ASK_STORIES_ENDPOINT: &str = "askstories";
 const JOBS_ENDPOINT: &str = "jobstories";
+const UPDATES_ENDPOINT: &str = "updates";
+const MAX_ITEM_ENDPOINT: &str = "maxitem";
 
 const ITEM_ENDPOINT: &str = "item/{}";
 const USER_ENDPOINT: &str = "user/{}";
 
-static FIREBASE: Lazy<Arc<FirebaseCache>> = Lazy::new(|| {
-    Arc::new(FirebaseCache::new(
-        API_BASE_URL,
-        std::time::Duration::from_secs(60 * 5),
-    ))
-});
-
-pub fn firebase() -> Arc<FirebaseCache> {
-    FIREBASE.clone()
-}
-
 #[derive(Clone, Copy, PartialEq, Debug, Eq, Hash, Serialize, Deserialize)]
 pub enum StoryType {
     Top,
     New,
     Show,
     Best,
+    Ask,
     Jobs,
 }
 
@@ -43,6 +35,7 @@ impl Display for StoryType {
             StoryType::New => "New",
             StoryType::Show => "Show",
             StoryType::Best => "Best",
+            StoryType::Ask => "Ask",
             StoryType::Jobs => "Jobs",
         };
         write!(f, "{}", topic)
@@ -55,6 +48,7 @@ pub fn get_stories_url(endpoint: StoryType) -> String {
         StoryType::New => NEW_STORIES_ENDPOINT,
         StoryType::Show => SHOW_STORIES_ENDPOINT,
         StoryType::Best => BEST_STORIES_ENDPOINT,
+        StoryType::Ask => ASK_STORIES_ENDPOINT,
         StoryType::Jobs => JOBS_ENDPOINT,
     };
     endpoint.to_string()
@@ -68,21 +62,25 @@ pub fn get_user_url(username: &str) -> String {
     USER_ENDPOINT.replace("{}", username)
 }
 
+pub fn get_updates_url() -> String {
+    UPDATES_ENDPOINT.to_string()
+}
+
+pub fn get_max_item_url() -> String {
+    MAX_ITEM_ENDPOINT.to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_get_firebase() {
-        let _ = firebase();
-    }
-
     #[test]
     fn test_get_stories_url() {
         assert_eq!(get_stories_url(StoryType::Top), TOP_STORIES_ENDPOINT);
         assert_eq!(get_stories_url(StoryType::New), NEW_STORIES_ENDPOINT);
         assert_eq!(get_stories_url(StoryType::Show), SHOW_STORIES_ENDPOINT);
         assert_eq!(get_stories_url(StoryType::Best), BEST_STORIES_ENDPOINT);
+        assert_eq!(get_stories_url(StoryType::Ask), ASK_STORIES_ENDPOINT);
         assert_eq!(get_stories_url(StoryType::Jobs), JOBS_ENDPOINT);
     }
 
@@ -92,6 +90,12 @@ mod tests {
         assert_eq!(get_item_url(item_id), format!("item/{}", item_id));
     }
 
+    #[test]
+    fn test_live_urls() {
+        assert_eq!(get_updates_url(), "updates");
+        assert_eq!(get_max_item_url(), "maxitem");
+    }
+
     #[test]
     fn test_get_user_url() {
         let username = "testuser";
@@ -1,14 +1,18 @@
-use firebase_rs::{Firebase, RequestError};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use std::collections::HashMap;
 use std::fmt::Debug;
-use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::{Semaphore, watch};
 
+use crate::disk_cache::{DiskCache, DiskEntry};
+use crate::error::{HnError, HnResult};
 use crate::get_items::ItemResponse;
 use crate::get_stories::StoriesResponse;
 use crate::get_user::User;
+use crate::memory_cache::{CacheLimits, CacheStats, MemoryCache};
+use crate::transport::Transport;
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub enum CacheItemType {
@@ -17,83 +21,368 @@ pub enum CacheItemType {
     User(User),
 }
 
-#[derive(Clone)]
-pub struct CacheData {
-    value: CacheItemType,
-    expire: Instant,
-}
+/// Result of an in-flight fetch, `None` until the leading request finishes.
+type Flight = watch::Receiver<Option<HnResult<CacheItemType>>>;
 
 pub struct FirebaseCache {
-    firebase: Firebase,
-    cache: Arc<RwLock<HashMap<String, CacheData>>>,
+    transport: Arc<dyn Transport>,
+    inflight: Mutex<HashMap<String, Flight>>,
+    cache: Mutex<MemoryCache>,
     max_age: Duration,
+    timeout: Duration,
+    limiter: Semaphore,
+    max_concurrent_requests: usize,
+    disk: Option<DiskCache>,
 }
 
 impl FirebaseCache {
-    pub fn new(url: &str, max_age: Duration) -> Self {
+    pub fn new(
+        transport: Arc<dyn Transport>,
+        max_age: Duration,
+        timeout: Duration,
+        max_concurrent_requests: usize,
+    ) -> Self {
         FirebaseCache {
-            firebase: Firebase::new(url).expect("Failed to create Firebase instance"),
-            cache: Arc::new(RwLock::new(HashMap::new())),
+            transport,
+            inflight: Mutex::new(HashMap::new()),
+            cache: Mutex::new(MemoryCache::new(CacheLimits::default())),
             max_age,
+            timeout,
+            limiter: Semaphore::new(max_concurrent_requests),
+            max_concurrent_requests,
+            disk: None,
+        }
+    }
+
+    pub fn with_limits(mut self, limits: CacheLimits) -> Self {
+        self.cache = Mutex::new(MemoryCache::new(limits));
+        self
+    }
+
+    /// Backs the in-memory map with a directory of JSON files.
+    pub fn with_disk_cache(mut self, disk: DiskCache) -> Self {
+        self.disk = Some(disk);
+        self
+    }
+
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn max_concurrent_requests(&self) -> usize {
+        self.max_concurrent_requests
+    }
+
+    pub fn transport(&self) -> &dyn Transport {
+        self.transport.as_ref()
+    }
+
+    pub fn disk(&self) -> Option<&DiskCache> {
+        self.disk.as_ref()
+    }
+
+    pub fn limits(&self) -> CacheLimits {
+        self.memory().limits()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.memory().stats()
+    }
+
+    pub fn sweep_expired(&self) -> usize {
+        self.memory().sweep_expired()
+    }
+
+    fn memory(&self) -> MutexGuard<'_, MemoryCache> {
+        // The map is always left consistent, so a poisoned lock is still usable.
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub async fn get(&self, key: &str) -> HnResult<CacheItemType> {
+        if let Some(value) = self.memory().get(key) {
+            return Ok(value);
+        }
+
+        if let Some(value) = self.load_from_disk(key).await {
+            return Ok(value);
+        }
+
+        self.single_flight(key, || self.fetch_and_store(key)).await
+    }
+
+    /// Runs `fetch` for `key` unless a fetch for it is already running, in
+    /// which case its result is shared instead.
+    async fn single_flight<F, Fut>(&self, key: &str, fetch: F) -> HnResult<CacheItemType>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = HnResult<CacheItemType>>,
+    {
+        loop {
+            // Concurrent misses on one key share a single request: the first
+            // caller fetches, the others wait for its result.
+            let leader = {
+                let mut inflight = self.inflight.lock().unwrap_or_else(|e| e.into_inner());
+                match inflight.get(key) {
+                    Some(flight) => Err(flight.clone()),
+                    None => {
+                        let (tx, rx) = watch::channel(None);
+                        inflight.insert(key.to_string(), rx);
+                        Ok(tx)
+                    }
+                }
+            };
+
+            match leader {
+                Ok(tx) => {
+                    let _guard = FlightGuard {
+                        inflight: &self.inflight,
+                        key,
+                    };
+                    let res = fetch().await;
+                    let _ = tx.send(Some(res.clone()));
+                    return res;
+                }
+                Err(mut flight) => {
+                    if let Ok(res) = flight.wait_for(Option::is_some).await {
+                        return res.clone().expect("waited for a result");
+                    }
+                    // The leading request was cancelled before it finished,
+                    // so start over and possibly lead the next one.
+                }
+            }
         }
     }
 
-    pub async fn get(&self, key: &str) -> Result<CacheItemType, RequestError> {
-        {
-            let cache = self.cache.read().await;
-            if let Some(data) = cache.get(key) {
-                if data.expire > Instant::now() {
-                    return Ok(data.value.clone());
-                } else {
-                    drop(cache);
+    async fn load_from_disk(&self, key: &str) -> Option<CacheItemType> {
+        let entry = self.disk.as_ref()?.load(key).await?;
+        let remaining = entry.remaining()?;
+
+        let mut memory = self.memory();
+        memory.record_disk_hit();
+        memory.insert(key.to_string(), entry.value.clone(), remaining);
+        Some(entry.value)
+    }
+
+    async fn fetch_and_store(&self, key: &str) -> HnResult<CacheItemType> {
+        match self.fetch_from_firebase(key).await {
+            Ok(res) => {
+                self.set(key.to_string(), res.clone()).await;
+                Ok(res)
+            }
+            Err(err) => {
+                // The upstream copy is gone, so a stale one must not be served either.
+                if matches!(err, HnError::NotFound(_)) {
                     self.remove(key).await;
                 }
+                Err(err)
             }
         }
+    }
 
-        let res = self.fetch_from_firebase(key).await?;
-        self.set(key.to_string(), res.clone()).await;
+    async fn fetch_from_firebase(&self, key: &str) -> HnResult<CacheItemType> {
+        self.limited(key, self.request(key)).await
+    }
+
+    /// Fetches `key` without reading or filling the cache, for endpoints
+    /// such as `updates` whose whole point is to be fresh.
+    pub async fn fetch_live<T: DeserializeOwned>(&self, key: &str) -> HnResult<T> {
+        self.limited(key, self.fetch_json(key)).await
+    }
+
+    /// Runs `request` under the concurrency limit and the client timeout.
+    async fn limited<T>(
+        &self,
+        key: &str,
+        request: impl Future<Output = HnResult<T>>,
+    ) -> HnResult<T> {
+        let _permit = self
+            .limiter
+            .acquire()
+            .await
+            .map_err(|_| HnError::Network(key.to_string()))?;
 
-        Ok(res)
+        tokio::time::timeout(self.timeout, request)
+            .await
+            .map_err(|_| HnError::Timeout(self.timeout))?
     }
 
-    async fn fetch_from_firebase(&self, key: &str) -> Result<CacheItemType, RequestError> {
+    async fn request(&self, key: &str) -> HnResult<CacheItemType> {
         if key.starts_with("item/") {
-            let item: ItemResponse = self.firebase.at(key).get().await?;
-            Ok(CacheItemType::Item(item))
+            Ok(CacheItemType::Item(self.fetch_json(key).await?))
         } else if key.contains("stories") {
-            let story: StoriesResponse = self.firebase.at(key).get().await?;
-            Ok(CacheItemType::Story(story))
+            Ok(CacheItemType::Story(self.fetch_json(key).await?))
         } else if key.starts_with("user/") {
-            let user: User = self.firebase.at(key).get().await?;
-            Ok(CacheItemType::User(user))
+            Ok(CacheItemType::User(self.fetch_json(key).await?))
         } else {
-            Err(RequestError::SerializeError)
+            Err(HnError::UnknownKey(key.to_string()))
+        }
+    }
+
+    async fn fetch_json<T: DeserializeOwned>(&self, key: &str) -> HnResult<T> {
+        let value = self.transport.fetch(key).await?;
+        serde_json::from_value(value).map_err(|_| HnError::Decode(key.to_string()))
+    }
+
+    /// Returns the last known value for `key` even if it has expired, without
+    /// touching the network.
+    pub async fn get_stale(&self, key: &str) -> Option<CacheItemType> {
+        if let Some(value) = self.memory().peek(key).cloned() {
+            return Some(value);
         }
+
+        let disk = self.disk.as_ref()?;
+        disk.load(key).await.map(|entry| entry.value)
     }
 
     pub async fn set(&self, key: String, value: CacheItemType) {
-        let expire = Instant::now() + self.max_age;
-        let mut cache = self.cache.write().await;
-        cache.insert(key, CacheData { value, expire });
+        if let Some(disk) = &self.disk {
+            disk.store_behind(&key, DiskEntry::new(value.clone(), self.max_age));
+        }
+
+        self.memory().insert(key, value, self.max_age);
     }
 
     pub async fn remove(&self, key: &str) {
-        let mut cache = self.cache.write().await;
-        cache.remove(key);
+        if let Some(disk) = &self.disk {
+            disk.remove(key).await;
+        }
+
+        self.memory().remove(key);
+    }
+}
+
+/// Clears the in-flight marker once the leading request ends, including when
+/// its task is aborted.
+struct FlightGuard<'a> {
+    inflight: &'a Mutex<HashMap<String, Flight>>,
+    key: &'a str,
+}
+
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        let mut inflight = self.inflight.lock().unwrap_or_else(|e| e.into_inner());
+        inflight.remove(self.key);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{test_util::temp_path, transport::FixtureTransport};
+    use futures::future::{BoxFuture, join_all};
+    use serde_json::{Value, json};
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
     use std::time::Duration;
 
+    /// Serves fixtures after `delay`, counting the requests that reach it.
+    struct CountingTransport {
+        fixtures: FixtureTransport,
+        delay: Duration,
+        calls: AtomicUsize,
+    }
+
+    impl CountingTransport {
+        fn new(fixtures: FixtureTransport, delay: Duration) -> Arc<Self> {
+            Arc::new(CountingTransport {
+                fixtures,
+                delay,
+                calls: AtomicUsize::new(0),
+            })
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Transport for CountingTransport {
+        fn fetch<'a>(&'a self, path: &'a str) -> BoxFuture<'a, HnResult<Value>> {
+            Box::pin(async move {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(self.delay).await;
+                self.fixtures.fetch(path).await
+            })
+        }
+    }
+
+    fn counting_cache(transport: &Arc<CountingTransport>) -> FirebaseCache {
+        FirebaseCache::new(
+            transport.clone(),
+            Duration::from_secs(60),
+            Duration::from_secs(5),
+            4,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_misses_share_one_fetch() {
+        let transport = CountingTransport::new(
+            FixtureTransport::new().with("topstories", json!([1, 2, 3])),
+            Duration::from_millis(50),
+        );
+        let cache = counting_cache(&transport);
+
+        let results = join_all((0..10).map(|_| cache.get("topstories"))).await;
+
+        assert_eq!(transport.calls(), 1);
+        for res in results {
+            assert_eq!(res, Ok(CacheItemType::Story(vec![1, 2, 3])));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_misses_share_errors() {
+        let transport = CountingTransport::new(FixtureTransport::new(), Duration::from_millis(50));
+        let cache = counting_cache(&transport);
+
+        let results = join_all((0..5).map(|_| cache.get("item/1"))).await;
+
+        assert_eq!(transport.calls(), 1);
+        for res in results {
+            assert_eq!(res, Err(HnError::NotFound("item/1".to_string())));
+        }
+
+        // Failures are not kept, so the next miss fetches again.
+        let _ = cache.get("item/1").await;
+        assert_eq!(transport.calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_leader_hands_over() {
+        let transport = CountingTransport::new(
+            FixtureTransport::new().with("newstories", json!([7])),
+            Duration::from_millis(50),
+        );
+        let cache = Arc::new(counting_cache(&transport));
+        let spawn_get = || {
+            let cache = cache.clone();
+            tokio::spawn(async move { cache.get("newstories").await })
+        };
+
+        let leader = spawn_get();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let follower = spawn_get();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        leader.abort();
+
+        assert_eq!(follower.await.unwrap(), Ok(CacheItemType::Story(vec![7])));
+        assert_eq!(transport.calls(), 2);
+    }
+
     #[tokio::test]
     async fn test_firebase_cache() {
         let cache = FirebaseCache::new(
-            "https://hacker-news.firebaseio.com/v0/",
+            Arc::new(FixtureTransport::new()),
+            Duration::from_millis(20),
             Duration::from_secs(5),
+            1,
         );
 
         let user = User {
@@ -108,9 +397,57 @@ mod tests {
             .await;
         let fetched_item = cache.get("user/1").await.unwrap();
         assert_eq!(fetched_item, CacheItemType::User(user.clone()));
+        assert_eq!(cache.stats().hits, 1);
 
-        tokio::time::sleep(Duration::from_secs(6)).await;
+        tokio::time::sleep(Duration::from_millis(30)).await;
         let expired_item = cache.get("user/1").await;
-        assert!(expired_item.is_err());
+        assert_eq!(expired_item, Err(HnError::NotFound("user/1".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_key() {
+        let cache = FirebaseCache::new(
+            Arc::new(FixtureTransport::new()),
+            Duration::from_secs(5),
+            Duration::from_secs(5),
+            1,
+        );
+
+        let res = cache.get("unknown").await;
+        assert_eq!(res, Err(HnError::UnknownKey("unknown".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_disk_tier() {
+        let dir = temp_path("hackernews_cache_test");
+        let disk = DiskCache::new(&dir);
+        let story = CacheItemType::Story(vec![1, 2, 3]);
+        disk.store(
+            "topstories",
+            &DiskEntry::new(story.clone(), Duration::from_secs(60)),
+        )
+        .await
+        .unwrap();
+        disk.store("newstories", &DiskEntry::new(story.clone(), Duration::ZERO))
+            .await
+            .unwrap();
+
+        let cache = FirebaseCache::new(
+            Arc::new(FixtureTransport::new()),
+            Duration::from_secs(60),
+            Duration::from_secs(5),
+            1,
+        )
+        .with_disk_cache(disk);
+
+        // A fresh entry on disk is served without a request.
+        assert_eq!(cache.get("topstories").await.unwrap(), story);
+        // A stale one is still available to readers that accept it.
+        assert_eq!(cache.get_stale("newstories").await, Some(story));
+
+        cache.remove("topstories").await;
+        assert_eq!(cache.get_stale("topstories").await, None);
+
+        let _ = std::fs::remove_dir_all(dir);
     }
 }
@@ -0,0 +1,294 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    api_url::API_BASE_URL,
+    cache::FirebaseCache,
+    disk_cache::{DiskCache, DiskLimits},
+    error::HnResult,
+    memory_cache::{CacheLimits, CacheStats},
+    transport::{FirebaseTransport, Transport},
+};
+
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60 * 5);
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 32;
+
+static DEFAULT_CLIENT: Lazy<HackerNewsClient> = Lazy::new(|| {
+    HackerNewsClient::builder()
+        .build()
+        .expect("The default Hacker News API URL is valid")
+});
+
+/// The client used by the free functions such as [`crate::get_items::get_item`].
+pub fn default_client() -> &'static HackerNewsClient {
+    &DEFAULT_CLIENT
+}
+
+/// Entry point to the Hacker News API.
+///
+/// Cloning is cheap: clones share the same cache and request limit.
+#[derive(Clone)]
+pub struct HackerNewsClient {
+    pub(crate) cache: Arc<FirebaseCache>,
+}
+
+impl HackerNewsClient {
+    pub fn builder() -> HackerNewsClientBuilder {
+        HackerNewsClientBuilder::default()
+    }
+
+    pub fn cache_ttl(&self) -> Duration {
+        self.cache.max_age()
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.cache.timeout()
+    }
+
+    pub fn max_concurrent_requests(&self) -> usize {
+        self.cache.max_concurrent_requests()
+    }
+
+    pub fn disk_cache_dir(&self) -> Option<&Path> {
+        self.cache.disk().map(|disk| disk.dir())
+    }
+
+    pub fn disk_cache_limits(&self) -> Option<DiskLimits> {
+        self.cache.disk().map(|disk| disk.limits())
+    }
+
+    pub fn cache_limits(&self) -> CacheLimits {
+        self.cache.limits()
+    }
+
+    /// Hit, miss and eviction counters of the in-memory cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    /// Drops expired entries now instead of waiting for the next sweep.
+    pub fn sweep_expired(&self) -> usize {
+        self.cache.sweep_expired()
+    }
+}
+
+#[derive(Clone)]
+pub struct HackerNewsClientBuilder {
+    base_url: String,
+    cache_ttl: Duration,
+    timeout: Duration,
+    max_concurrent_requests: usize,
+    disk_cache_dir: Option<PathBuf>,
+    disk_cache_limits: DiskLimits,
+    cache_limits: CacheLimits,
+    transport: Option<Arc<dyn Transport>>,
+}
+
+impl Default for HackerNewsClientBuilder {
+    fn default() -> Self {
+        Self {
+            base_url: API_BASE_URL.to_string(),
+            cache_ttl: DEFAULT_CACHE_TTL,
+            timeout: DEFAULT_TIMEOUT,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            disk_cache_dir: None,
+            disk_cache_limits: DiskLimits::default(),
+            cache_limits: CacheLimits::default(),
+            transport: None,
+        }
+    }
+}
+
+impl HackerNewsClientBuilder {
+    /// Root of the API, e.g. `https://hacker-news.firebaseio.com/v0/`.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// How long a fetched response is served from the cache.
+    pub fn cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    /// Upper bound for a single request, including reading the body.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Maximum number of requests in flight at once. Clamped to at least 1.
+    pub fn max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests.max(1);
+        self
+    }
+
+    /// Persists responses under `dir` so they survive restarts. Entries are
+    /// loaded lazily on a memory miss and written in the background.
+    pub fn disk_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.disk_cache_dir = Some(dir.into());
+        self
+    }
+
+    /// File count and how long expired entries are kept on disk.
+    pub fn disk_cache_limits(mut self, disk_cache_limits: DiskLimits) -> Self {
+        self.disk_cache_limits = disk_cache_limits;
+        self
+    }
+
+    /// Entry count, byte budget and sweep interval of the in-memory cache.
+    pub fn cache_limits(mut self, cache_limits: CacheLimits) -> Self {
+        self.cache_limits = cache_limits;
+        self
+    }
+
+    /// Replaces the HTTP transport, e.g. with a
+    /// [`FixtureTransport`](crate::transport::FixtureTransport) for offline
+    /// use. The base URL is ignored when a transport is set.
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    pub fn build(self) -> HnResult<HackerNewsClient> {
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => Arc::new(FirebaseTransport::new(&self.base_url)?),
+        };
+        let mut cache = FirebaseCache::new(
+            transport,
+            self.cache_ttl,
+            self.timeout,
+            self.max_concurrent_requests,
+        )
+        .with_limits(self.cache_limits);
+        if let Some(dir) = self.disk_cache_dir {
+            cache = cache.with_disk_cache(DiskCache::new(dir).with_limits(self.disk_cache_limits));
+        }
+
+        let cache = Arc::new(cache);
+        spawn_sweeper(&cache);
+
+        Ok(HackerNewsClient { cache })
+    }
+}
+
+/// Sweeps expired entries out of the memory cache every `sweep_interval`
+/// until the last clone of the client is dropped. Outside of a runtime they
+/// are only swept as the cache is read.
+fn spawn_sweeper(cache: &Arc<FirebaseCache>) {
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    let period = cache.limits().sweep_interval;
+    if period.is_zero() {
+        return;
+    }
+
+    let cache = Arc::downgrade(cache);
+    runtime.spawn(async move {
+        let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            ticks.tick().await;
+            let Some(cache) = cache.upgrade() else {
+                break;
+            };
+            cache.sweep_expired();
+        }
+    });
+}
+
+/// A client that serves the JSON files under `fixtures/`.
+#[cfg(test)]
+pub(crate) fn fixture_client() -> HackerNewsClient {
+    HackerNewsClient::builder()
+        .transport(crate::transport::FixtureTransport::from_dir(
+            crate::test_util::fixtures_dir(),
+        ))
+        .build()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheItemType;
+
+    #[test]
+    fn test_default_client() {
+        let _ = default_client();
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_url() {
+        let client = HackerNewsClient::builder().base_url("not a url").build();
+        assert!(client.is_err());
+    }
+
+    #[test]
+    fn test_builder_options() {
+        let client = HackerNewsClient::builder()
+            .cache_ttl(Duration::from_secs(1))
+            .timeout(Duration::from_millis(500))
+            .max_concurrent_requests(0)
+            .build()
+            .unwrap();
+        assert_eq!(client.cache_ttl(), Duration::from_secs(1));
+        assert_eq!(client.timeout(), Duration::from_millis(500));
+        assert_eq!(client.max_concurrent_requests(), 1);
+        assert_eq!(client.disk_cache_dir(), None);
+        assert_eq!(client.disk_cache_limits(), None);
+        assert_eq!(client.cache_limits(), CacheLimits::default());
+
+        let limits = CacheLimits {
+            max_entries: Some(10),
+            max_bytes: None,
+            sweep_interval: Duration::from_secs(1),
+        };
+        let disk_limits = DiskLimits {
+            max_entries: None,
+            keep_stale: Duration::ZERO,
+        };
+        let client = HackerNewsClient::builder()
+            .disk_cache_dir("/tmp/hn")
+            .disk_cache_limits(disk_limits)
+            .cache_limits(limits)
+            .build()
+            .unwrap();
+        assert_eq!(client.disk_cache_dir(), Some(Path::new("/tmp/hn")));
+        assert_eq!(client.disk_cache_limits(), Some(disk_limits));
+        assert_eq!(client.cache_limits(), limits);
+        assert_eq!(client.cache_stats(), CacheStats::default());
+    }
+
+    #[tokio::test]
+    async fn test_idle_cache_is_swept() {
+        let client = HackerNewsClient::builder()
+            .transport(crate::transport::FixtureTransport::new())
+            .cache_ttl(Duration::from_millis(10))
+            .cache_limits(CacheLimits {
+                sweep_interval: Duration::from_millis(50),
+                ..CacheLimits::default()
+            })
+            .build()
+            .unwrap();
+        client
+            .cache
+            .set("topstories".to_string(), CacheItemType::Story(vec![1]))
+            .await;
+        assert_eq!(client.cache_stats().entries, 1);
+
+        // Nothing reads the cache in the meantime.
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        let stats = client.cache_stats();
+        assert_eq!(stats.entries, 0);
+        assert_eq!(stats.expirations, 1);
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    collections::HashMap,
+    io::Result,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::CacheItemType;
+
+/// A cache entry as stored on disk. The expiry is wall-clock time so that it
+/// survives restarts.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct DiskEntry {
+    pub expires_at: u64,
+    pub value: CacheItemType,
+}
+
+impl DiskEntry {
+    pub fn new(value: CacheItemType, ttl: Duration) -> Self {
+        DiskEntry {
+            expires_at: unix_now().saturating_add(ttl.as_secs()),
+            value,
+        }
+    }
+
+    /// Time left before the entry expires, `None` once it is stale.
+    pub fn remaining(&self) -> Option<Duration> {
+        let now = unix_now();
+        (self.expires_at > now).then(|| Duration::from_secs(self.expires_at - now))
+    }
+
+    /// How long ago the entry expired, zero while it is fresh.
+    fn stale_for(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.expires_at))
+    }
+}
+
+/// How much the disk tier keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskLimits {
+    /// Files kept at most. The least recently written go first.
+    pub max_entries: Option<usize>,
+    /// How long an expired entry is still served to offline readers. It is
+    /// deleted the next time it is read after that.
+    pub keep_stale: Duration,
+}
+
+impl Default for DiskLimits {
+    fn default() -> Self {
+        DiskLimits {
+            max_entries: Some(20_000),
+            keep_stale: Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+}
+
+/// The file count is checked once every this many writes.
+const PRUNE_EVERY: u64 = 100;
+
+/// Second cache tier that keeps one JSON file per key in `dir`.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    limits: DiskLimits,
+    /// The latest background write queued for each key. A write only lands
+    /// if it is still the one recorded here, so a later write or removal of
+    /// the key always wins.
+    pending: Arc<Mutex<HashMap<String, u64>>>,
+    writes: Arc<AtomicU64>,
+    /// Held while a file is written or removed.
+    io: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl DiskCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DiskCache {
+            dir: dir.into(),
+            limits: DiskLimits::default(),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            writes: Arc::new(AtomicU64::new(0)),
+            io: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: DiskLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn limits(&self) -> DiskLimits {
+        self.limits
+    }
+
+    fn pending(&self) -> MutexGuard<'_, HashMap<String, u64>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let file_name = key
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        self.dir.join(format!("{}.json", file_name))
+    }
+
+    /// Reads the entry for `key`, whether it is fresh or not. Entries that
+    /// expired longer ago than [`DiskLimits::keep_stale`], and files that do
+    /// not parse, are deleted instead.
+    pub async fn load(&self, key: &str) -> Option<DiskEntry> {
+        let bytes = tokio::fs::read(self.path(key)).await.ok()?;
+        match serde_json::from_slice::<DiskEntry>(&bytes) {
+            Ok(entry) if entry.stale_for() <= self.limits.keep_stale => Some(entry),
+            _ => {
+                self.discard(key).await;
+                None
+            }
+        }
+    }
+
+    pub async fn store(&self, key: &str, entry: &DiskEntry) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let bytes = serde_json::to_vec(entry)?;
+        tokio::fs::write(self.path(key), bytes).await
+    }
+
+    /// Writes the entry in a background task so callers never wait on disk.
+    pub fn store_behind(&self, key: &str, entry: DiskEntry) {
+        let write = self.writes.fetch_add(1, Ordering::Relaxed);
+        self.pending().insert(key.to_string(), write);
+
+        let disk = self.clone();
+        let key = key.to_string();
+        tokio::spawn(async move {
+            let _io = disk.io.lock().await;
+            if disk.pending().get(&key) != Some(&write) {
+                return;
+            }
+            let _ = disk.store(&key, &entry).await;
+            {
+                let mut pending = disk.pending();
+                if pending.get(&key) == Some(&write) {
+                    pending.remove(&key);
+                }
+            }
+            if write.is_multiple_of(PRUNE_EVERY) {
+                let _ = disk.prune().await;
+            }
+        });
+    }
+
+    /// Deletes the entry, including one still waiting to be written.
+    pub async fn remove(&self, key: &str) {
+        self.pending().remove(key);
+        let _io = self.io.lock().await;
+        let _ = tokio::fs::remove_file(self.path(key)).await;
+    }
+
+    /// Deletes the file for `key` unless a newer entry is about to replace it.
+    async fn discard(&self, key: &str) {
+        let _io = self.io.lock().await;
+        if !self.pending().contains_key(key) {
+            let _ = tokio::fs::remove_file(self.path(key)).await;
+        }
+    }
+
+    /// Deletes the least recently written files past
+    /// [`DiskLimits::max_entries`]. Returns how many were deleted.
+    async fn prune(&self) -> Result<usize> {
+        let Some(max_entries) = self.limits.max_entries else {
+            return Ok(0);
+        };
+
+        let mut files = Vec::new();
+        let mut dir = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(file) = dir.next_entry().await? {
+            let path = file.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                files.push((file.metadata().await?.modified()?, path));
+            }
+        }
+
+        let excess = files.len().saturating_sub(max_entries);
+        files.sort();
+        for (_, path) in files.into_iter().take(excess) {
+            let _ = tokio::fs::remove_file(path).await;
+        }
+        Ok(excess)
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_path;
+
+    fn temp_dir() -> PathBuf {
+        temp_path("hackernews_disk_cache_test")
+    }
+
+    #[tokio::test]
+    async fn test_store_and_load() {
+        let disk = DiskCache::new(temp_dir());
+        let entry = DiskEntry::new(CacheItemType::Story(vec![1, 2, 3]), Duration::from_secs(60));
+
+        disk.store("topstories", &entry).await.unwrap();
+        let loaded = disk.load("topstories").await.unwrap();
+        assert_eq!(loaded, entry);
+        assert!(loaded.remaining().is_some());
+
+        disk.remove("topstories").await;
+        assert!(disk.load("topstories").await.is_none());
+
+        let _ = std::fs::remove_dir_all(disk.dir());
+    }
+
+    #[tokio::test]
+    async fn test_later_write_or_removal_wins() {
+        let disk = DiskCache::new(temp_dir());
+        let entry = |id| DiskEntry::new(CacheItemType::Story(vec![id]), Duration::from_secs(60));
+
+        disk.store_behind("topstories", entry(1));
+        disk.store_behind("topstories", entry(2));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let loaded = disk.load("topstories").await.map(|entry| entry.value);
+        assert_eq!(loaded, Some(CacheItemType::Story(vec![2])));
+
+        disk.store_behind("topstories", entry(3));
+        disk.remove("topstories").await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(disk.load("topstories").await, None);
+
+        let _ = std::fs::remove_dir_all(disk.dir());
+    }
+
+    #[tokio::test]
+    async fn test_long_expired_entry_is_deleted() {
+        let disk = DiskCache::new(temp_dir());
+        let stale = DiskEntry::new(CacheItemType::Story(vec![1]), Duration::ZERO);
+        let expired = DiskEntry {
+            expires_at: 0,
+            value: CacheItemType::Story(vec![2]),
+        };
+        disk.store("newstories", &stale).await.unwrap();
+        disk.store("topstories", &expired).await.unwrap();
+
+        // Recently expired entries are kept for offline reads.
+        assert_eq!(disk.load("newstories").await, Some(stale));
+        assert_eq!(disk.load("topstories").await, None);
+        assert!(!disk.path("topstories").exists());
+
+        let _ = std::fs::remove_dir_all(disk.dir());
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_the_newest_files() {
+        let disk = DiskCache::new(temp_dir()).with_limits(DiskLimits {
+            max_entries: Some(2),
+            ..DiskLimits::default()
+        });
+        let entry = DiskEntry::new(CacheItemType::Story(vec![]), Duration::from_secs(60));
+        for key in ["item/1", "item/2", "item/3"] {
+            disk.store(key, &entry).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(disk.prune().await.unwrap(), 1);
+        assert!(disk.load("item/1").await.is_none());
+        assert!(disk.load("item/2").await.is_some());
+        assert!(disk.load("item/3").await.is_some());
+
+        let _ = std::fs::remove_dir_all(disk.dir());
+    }
+
+    #[test]
+    fn test_expired_entry() {
+        let entry = DiskEntry {
+            expires_at: 0,
+            value: CacheItemType::Story(vec![]),
+        };
+        assert!(entry.remaining().is_none());
+    }
+
+    #[test]
+    fn test_path_is_flat() {
+        let disk = DiskCache::new("/tmp/hn");
+        assert_eq!(disk.path("item/42"), PathBuf::from("/tmp/hn/item_42.json"));
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    time::Duration,
+};
+
+pub type HnResult<T> = Result<T, HnError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HnError {
+    /// The API answered `null`: the item or user does not exist.
+    NotFound(String),
+    /// The request could not be sent or the server answered with an error status.
+    Network(String),
+    /// The response body does not match the expected schema.
+    Decode(String),
+    /// The request did not finish within the client timeout.
+    Timeout(Duration),
+    /// The key does not map to any known endpoint.
+    UnknownKey(String),
+    /// The base URL given to the client builder is not usable.
+    InvalidUrl(String),
+}
+
+impl HnError {
+    /// Whether retrying the same request later may succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, HnError::Network(_) | HnError::Timeout(_))
+    }
+}
+
+impl Error for HnError {}
+
+impl Display for HnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HnError::NotFound(key) => write!(f, "{} does not exist", key),
+            HnError::Network(key) => write!(f, "Network error while fetching {}", key),
+            HnError::Decode(key) => write!(f, "Unexpected response for {}", key),
+            HnError::Timeout(after) => write!(f, "Request timed out after {:?}", after),
+            HnError::UnknownKey(key) => write!(f, "Unknown API path: {}", key),
+            HnError::InvalidUrl(reason) => write!(f, "Invalid API URL: {}", reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            HnError::NotFound("item/1".to_string()).to_string(),
+            "item/1 does not exist"
+        );
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(HnError::Timeout(Duration::from_secs(1)).is_transient());
+        assert!(HnError::Network("item/1".to_string()).is_transient());
+        assert!(!HnError::NotFound("item/1".to_string()).is_transient());
+    }
+}
@@ -1,9 +1,18 @@
-use firebase_rs::RequestError;
+use std::{collections::HashMap, pin::pin};
+
+use chrono::{DateTime, Utc};
+use futures::{
+    Stream, StreamExt,
+    stream::{self, BoxStream},
+};
+use reqwest::Url;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    api_url::{firebase, get_item_url},
+    api_url::{HN_ITEM_URL, get_item_url},
     cache::CacheItemType,
+    client::{HackerNewsClient, default_client},
+    error::{HnError, HnResult},
 };
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
@@ -16,25 +25,71 @@ pub enum ItemType {
     PollOpt,
 }
 
-#[allow(dead_code)]
+/// An item as served by `v0/item/<id>`: stories, comments, jobs, polls and
+/// poll options all share this shape. Fields the API omits are `None`.
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct ItemResponse {
+    /// The item's unique id.
     pub id: usize,
-    deleted: Option<bool>,
-    r#type: ItemType,
+    /// `Some(true)` if the item was deleted.
+    pub deleted: Option<bool>,
+    /// Whether this is a story, comment, job, poll or poll option.
+    #[serde(rename = "type")]
+    pub kind: ItemType,
+    /// Username of the author.
     pub by: Option<String>,
+    /// Creation time in Unix seconds, see [`ItemResponse::created_at`].
     pub time: usize,
+    /// Comment, story or poll text, as HTML.
     pub text: Option<String>,
-    dead: Option<bool>,
-    parent: Option<usize>,
-    poll: Option<usize>,
+    /// `Some(true)` if the item was flagged or killed.
+    pub dead: Option<bool>,
+    /// For comments, the parent comment or story. For poll options, the poll.
+    pub parent: Option<usize>,
+    /// For poll options, the poll they belong to.
+    pub poll: Option<usize>,
+    /// Ids of the direct replies, in ranked display order.
     pub kids: Option<Vec<usize>>,
+    /// The replies themselves, filled in by
+    /// [`HackerNewsClient::get_item_tree`]. Never sent by the API.
     pub children: Option<Vec<ItemResponse>>,
+    /// The linked URL of a story.
     pub url: Option<String>,
-    score: Option<usize>,
+    /// Points of a story or votes of a poll option.
+    pub score: Option<usize>,
+    /// Title of a story, poll or job.
     pub title: Option<String>,
-    parts: Option<Vec<usize>>,
-    descendants: Option<usize>,
+    /// For polls, the ids of their options in display order.
+    pub parts: Option<Vec<usize>>,
+    /// For stories and polls, the total comment count.
+    pub descendants: Option<usize>,
+}
+
+impl ItemResponse {
+    /// Creation time, or the Unix epoch if `time` is out of range.
+    pub fn created_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.time as i64, 0).unwrap_or_default()
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.dead.unwrap_or(false)
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted.unwrap_or(false)
+    }
+
+    /// Host of `url` without a leading `www.`, e.g. `github.com`.
+    pub fn domain(&self) -> Option<String> {
+        let url = Url::parse(self.url.as_deref()?).ok()?;
+        let host = url.host_str()?;
+        Some(host.strip_prefix("www.").unwrap_or(host).to_string())
+    }
+
+    /// Link to the item's discussion page on news.ycombinator.com.
+    pub fn hn_permalink(&self) -> String {
+        format!("{}{}", HN_ITEM_URL, self.id)
+    }
 }
 
 impl Default for ItemResponse {
@@ -42,7 +97,7 @@ impl Default for ItemResponse {
         ItemResponse {
             id: 0,
             deleted: None,
-            r#type: ItemType::Story,
+            kind: ItemType::Story,
             by: Some("Linux".to_string()),
             time: 0,
             text: Some("This is a default item".to_string()),
@@ -60,29 +115,365 @@ impl Default for ItemResponse {
     }
 }
 
-pub async fn get_item(item_id: usize) -> Result<ItemResponse, RequestError> {
-    let firebase = firebase();
-    let item_url = get_item_url(item_id);
-    let response = firebase.get(&item_url).await?;
+impl HackerNewsClient {
+    pub async fn get_item(&self, item_id: usize) -> HnResult<ItemResponse> {
+        let item_url = get_item_url(item_id);
+        let response = self.cache.get(&item_url).await?;
+
+        if let CacheItemType::Item(item) = response {
+            Ok(item)
+        } else {
+            Err(HnError::Decode(item_url))
+        }
+    }
+
+    /// Last cached copy of the item, possibly expired. Never hits the network.
+    pub async fn cached_item(&self, item_id: usize) -> Option<ItemResponse> {
+        match self.cache.get_stale(&get_item_url(item_id)).await {
+            Some(CacheItemType::Item(item)) => Some(item),
+            _ => None,
+        }
+    }
+
+    /// Drops the cached copy so that the next read fetches it again.
+    pub async fn invalidate_item(&self, item_id: usize) {
+        self.cache.remove(&get_item_url(item_id)).await;
+    }
+
+    /// Drops the cached copies of the item and of every descendant found in
+    /// the cache, so that the next tree read fetches the whole thread again.
+    pub async fn invalidate_item_tree(&self, item_id: usize) {
+        let mut pending = vec![item_id];
+        while let Some(id) = pending.pop() {
+            if let Some(item) = self.cached_item(id).await {
+                pending.extend(item.kids.iter().flatten());
+            }
+            self.invalidate_item(id).await;
+        }
+    }
+}
+
+/// Limits for [`HackerNewsClient::get_item_tree`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeOptions {
+    /// Deepest level to fetch, the root being level 0. `None` fetches the
+    /// whole tree.
+    pub max_depth: Option<usize>,
+    /// Maximum number of items of the tree requested at once.
+    pub max_concurrent_requests: usize,
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        TreeOptions {
+            max_depth: None,
+            max_concurrent_requests: 16,
+        }
+    }
+}
+
+impl TreeOptions {
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = max_concurrent_requests.max(1);
+        self
+    }
+
+    fn expands(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max| depth < max)
+    }
+}
+
+/// An item of a tree as it arrives from [`HackerNewsClient::stream_item_tree`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeNode {
+    pub item: ItemResponse,
+    pub depth: usize,
+    pub parent: Option<usize>,
+}
+
+impl HackerNewsClient {
+    /// Fetches `item_id` and its descendants, with `children` filled in the
+    /// order of `kids`. Descendants that fail to load are left out; items at
+    /// `max_depth` keep their `kids` but get no `children`.
+    pub async fn get_item_tree(
+        &self,
+        item_id: usize,
+        options: TreeOptions,
+    ) -> HnResult<ItemResponse> {
+        let mut nodes = HashMap::new();
+        let mut tree = pin!(self.stream_item_tree(item_id, options));
+        while let Some(node) = tree.next().await {
+            match node {
+                Ok(node) => {
+                    nodes.insert(node.item.id, node);
+                }
+                Err(err) if nodes.is_empty() => return Err(err),
+                Err(_) => {}
+            }
+        }
+
+        assemble_tree(&mut nodes, item_id, &options)
+            .ok_or_else(|| HnError::NotFound(get_item_url(item_id)))
+    }
+
+    /// Streams `item_id` and its descendants level by level as they are
+    /// fetched. Within a level, nodes come in the order of their parents'
+    /// `kids`. A failed root ends the stream after its error; failed
+    /// descendants are reported and skipped. Items are only requested while
+    /// the stream is polled, so dropping it stops the walk.
+    pub fn stream_item_tree(
+        &self,
+        item_id: usize,
+        options: TreeOptions,
+    ) -> impl Stream<Item = HnResult<TreeNode>> + Send + 'static {
+        let walk = TreeWalk {
+            fetches: self.fetch_level(vec![(item_id, None)], &options),
+            client: self.clone(),
+            options,
+            depth: 0,
+            next_level: Vec::new(),
+        };
 
-    if let CacheItemType::Item(item) = response {
-        Ok(item)
-    } else {
-        Err(RequestError::SerializeError)
+        stream::unfold(walk, |mut walk| async move {
+            loop {
+                if let Some((parent, res)) = walk.fetches.next().await {
+                    let node = res.map(|item| {
+                        if walk.options.expands(walk.depth) {
+                            let kids = item.kids.iter().flatten();
+                            walk.next_level
+                                .extend(kids.map(|&kid| (kid, Some(item.id))));
+                        }
+                        TreeNode {
+                            item,
+                            depth: walk.depth,
+                            parent,
+                        }
+                    });
+                    return Some((node, walk));
+                }
+
+                if walk.next_level.is_empty() {
+                    return None;
+                }
+                let level = std::mem::take(&mut walk.next_level);
+                walk.fetches = walk.client.fetch_level(level, &walk.options);
+                walk.depth += 1;
+            }
+        })
+    }
+
+    /// Requests the `(id, parent)` pairs of one level, yielding them in order.
+    fn fetch_level(
+        &self,
+        level: Vec<(usize, Option<usize>)>,
+        options: &TreeOptions,
+    ) -> LevelFetches {
+        let client = self.clone();
+        stream::iter(level)
+            .map(move |(id, parent)| {
+                let client = client.clone();
+                async move { (parent, client.get_item(id).await) }
+            })
+            .buffered(options.max_concurrent_requests.max(1))
+            .boxed()
     }
 }
 
+type LevelFetches = BoxStream<'static, (Option<usize>, HnResult<ItemResponse>)>;
+
+/// State of [`HackerNewsClient::stream_item_tree`] between two nodes.
+struct TreeWalk {
+    client: HackerNewsClient,
+    options: TreeOptions,
+    depth: usize,
+    fetches: LevelFetches,
+    next_level: Vec<(usize, Option<usize>)>,
+}
+
+fn assemble_tree(
+    nodes: &mut HashMap<usize, TreeNode>,
+    item_id: usize,
+    options: &TreeOptions,
+) -> Option<ItemResponse> {
+    let node = nodes.remove(&item_id)?;
+    let mut item = node.item;
+
+    item.children = match &item.kids {
+        Some(kids) if options.expands(node.depth) => Some(
+            kids.iter()
+                .filter_map(|&kid| assemble_tree(nodes, kid, options))
+                .collect(),
+        ),
+        _ => None,
+    };
+
+    Some(item)
+}
+
+pub async fn get_item(item_id: usize) -> HnResult<ItemResponse> {
+    default_client().get_item(item_id).await
+}
+
+pub async fn get_item_tree(item_id: usize, options: TreeOptions) -> HnResult<ItemResponse> {
+    default_client().get_item_tree(item_id, options).await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{client::fixture_client, transport::FixtureTransport};
+    use serde_json::json;
 
     #[tokio::test]
     async fn test_get_item() {
         let item_id = 8863; // Example item ID
-        let response = get_item(item_id).await;
+        let response = fixture_client().get_item(item_id).await;
         assert!(response.is_ok());
         let item = response.unwrap();
         assert_eq!(item.id, item_id);
-        assert!(item.r#type == ItemType::Story || item.r#type == ItemType::Job);
+        assert!(item.kind == ItemType::Story || item.kind == ItemType::Job);
+    }
+
+    fn thread_client() -> HackerNewsClient {
+        let comment = |id: usize, kids: &[usize]| json!({"id": id, "type": "comment", "time": 0, "kids": kids});
+        let transport = FixtureTransport::new()
+            .with(
+                "item/1",
+                json!({"id": 1, "type": "story", "time": 0, "kids": [3, 2]}),
+            )
+            .with("item/2", comment(2, &[]))
+            .with("item/3", comment(3, &[5, 4, 6]))
+            .with("item/4", comment(4, &[7]))
+            .with("item/5", comment(5, &[]))
+            .with("item/7", comment(7, &[]));
+        HackerNewsClient::builder()
+            .transport(transport)
+            .build()
+            .unwrap()
+    }
+
+    fn child_ids(item: &ItemResponse) -> Vec<usize> {
+        item.children
+            .iter()
+            .flatten()
+            .map(|child| child.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_get_item_tree() {
+        let root = thread_client()
+            .get_item_tree(1, TreeOptions::default().max_concurrent_requests(2))
+            .await
+            .unwrap();
+
+        // Children follow `kids`; the missing item 6 is left out.
+        assert_eq!(child_ids(&root), vec![3, 2]);
+        let first = &root.children.as_ref().unwrap()[0];
+        assert_eq!(child_ids(first), vec![5, 4]);
+        assert_eq!(child_ids(&first.children.as_ref().unwrap()[1]), vec![7]);
+    }
+
+    #[tokio::test]
+    async fn test_get_item_tree_max_depth() {
+        let root = thread_client()
+            .get_item_tree(1, TreeOptions::default().max_depth(1))
+            .await
+            .unwrap();
+
+        let first = &root.children.as_ref().unwrap()[0];
+        assert_eq!(first.kids, Some(vec![5, 4, 6]));
+        assert_eq!(first.children, None);
+    }
+
+    #[tokio::test]
+    async fn test_stream_item_tree() {
+        let nodes = thread_client()
+            .stream_item_tree(1, TreeOptions::default())
+            .collect::<Vec<_>>()
+            .await;
+
+        let ids = nodes
+            .into_iter()
+            .map(|node| node.map(|node| (node.item.id, node.depth, node.parent)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                Ok((1, 0, None)),
+                Ok((3, 1, Some(1))),
+                Ok((2, 1, Some(1))),
+                Ok((5, 2, Some(3))),
+                Ok((4, 2, Some(3))),
+                Err(HnError::NotFound("item/6".to_string())),
+                Ok((7, 3, Some(4))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stream_item_tree_outside_runtime() {
+        // Building the stream needs no runtime, only polling it does.
+        let tree = thread_client().stream_item_tree(1, TreeOptions::default().max_depth(1));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let nodes = runtime.block_on(tree.collect::<Vec<_>>());
+        assert_eq!(nodes.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_item_tree() {
+        let client = thread_client();
+        client
+            .get_item_tree(1, TreeOptions::default())
+            .await
+            .unwrap();
+        assert!(client.cached_item(7).await.is_some());
+
+        client.invalidate_item_tree(3).await;
+        for id in [3, 4, 5, 7] {
+            assert_eq!(client.cached_item(id).await, None);
+        }
+        assert!(client.cached_item(1).await.is_some());
+        assert!(client.cached_item(2).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_get_missing_item_tree() {
+        let res = thread_client()
+            .get_item_tree(42, TreeOptions::default())
+            .await;
+        assert_eq!(res, Err(HnError::NotFound("item/42".to_string())));
+    }
+
+    #[test]
+    fn test_item_helpers() {
+        let item = ItemResponse {
+            id: 8863,
+            time: 1175714200,
+            url: Some("https://www.getdropbox.com/u/2/screencast.html".to_string()),
+            dead: Some(true),
+            ..ItemResponse::default()
+        };
+
+        assert_eq!(item.created_at().to_rfc3339(), "2007-04-04T19:16:40+00:00");
+        assert_eq!(item.domain().as_deref(), Some("getdropbox.com"));
+        assert_eq!(
+            item.hn_permalink(),
+            "https://news.ycombinator.com/item?id=8863"
+        );
+        assert!(item.is_dead());
+        assert!(!item.is_deleted());
+        assert_eq!(ItemResponse::default().domain(), None);
+    }
+
+    #[tokio::test]
+    async fn test_get_missing_item() {
+        let response = fixture_client().get_item(1).await;
+        assert_eq!(response, Err(HnError::NotFound("item/1".to_string())));
     }
 }
@@ -0,0 +1,123 @@
+use futures::{StreamExt, stream};
+
+use crate::{
+    api_url::get_item_url,
+    client::{HackerNewsClient, default_client},
+    error::{HnError, HnResult},
+    get_items::{ItemResponse, ItemType},
+};
+
+/// Number of poll options requested at once.
+const POLL_OPTION_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PollOption {
+    pub id: usize,
+    pub text: String,
+    pub score: usize,
+}
+
+/// A poll together with its resolved options, in the order of `parts`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Poll {
+    pub item: ItemResponse,
+    pub options: Vec<PollOption>,
+}
+
+impl Poll {
+    pub fn total_votes(&self) -> usize {
+        self.options.iter().map(|option| option.score).sum()
+    }
+
+    /// Options sorted by score, highest first. Ties keep the poll's order.
+    pub fn ranked(&self) -> Vec<&PollOption> {
+        let mut options = self.options.iter().collect::<Vec<_>>();
+        options.sort_by_key(|option| std::cmp::Reverse(option.score));
+        options
+    }
+}
+
+impl HackerNewsClient {
+    /// Fetches a poll and all of its options. Options that no longer exist
+    /// are left out.
+    pub async fn get_poll(&self, poll_id: usize) -> HnResult<Poll> {
+        let item = self.get_item(poll_id).await?;
+        if item.kind != ItemType::Poll {
+            return Err(HnError::Decode(get_item_url(poll_id)));
+        }
+
+        let parts = item.parts.clone().unwrap_or_default();
+        let options = stream::iter(parts)
+            .map(|id| self.get_item(id))
+            .buffered(POLL_OPTION_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut poll_options = Vec::new();
+        for option in options {
+            match option {
+                Ok(option) if !option.is_deleted() => poll_options.push(PollOption {
+                    id: option.id,
+                    text: option.text.unwrap_or_default(),
+                    score: option.score.unwrap_or(0),
+                }),
+                Ok(_) | Err(HnError::NotFound(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(Poll {
+            item,
+            options: poll_options,
+        })
+    }
+}
+
+pub async fn get_poll(poll_id: usize) -> HnResult<Poll> {
+    default_client().get_poll(poll_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::FixtureTransport;
+    use serde_json::json;
+
+    fn option(id: usize, text: &str, score: usize) -> serde_json::Value {
+        json!({"id": id, "type": "pollopt", "time": 0, "text": text, "score": score})
+    }
+
+    fn poll_client() -> HackerNewsClient {
+        let transport = FixtureTransport::new()
+            .with(
+                "item/1",
+                json!({"id": 1, "type": "poll", "time": 0, "title": "Editor?", "parts": [2, 3, 4, 5]}),
+            )
+            .with("item/2", option(2, "vim", 10))
+            .with("item/3", option(3, "emacs", 30))
+            .with("item/4", option(4, "nano", 10))
+            .with("item/6", json!({"id": 6, "type": "story", "time": 0}));
+        HackerNewsClient::builder()
+            .transport(transport)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_poll() {
+        let poll = poll_client().get_poll(1).await.unwrap();
+
+        let ids = poll.options.iter().map(|o| o.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 3, 4]);
+        assert_eq!(poll.total_votes(), 50);
+
+        let ranked = poll.ranked().iter().map(|o| o.id).collect::<Vec<_>>();
+        assert_eq!(ranked, vec![3, 2, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_get_poll_rejects_other_items() {
+        let res = poll_client().get_poll(6).await;
+        assert_eq!(res, Err(HnError::Decode("item/6".to_string())));
+    }
+}
@@ -1,31 +1,50 @@
-use firebase_rs::RequestError;
-
 use crate::{
-    api_url::{StoryType, firebase, get_stories_url},
+    api_url::{StoryType, get_stories_url},
     cache::CacheItemType,
+    client::{HackerNewsClient, default_client},
+    error::{HnError, HnResult},
 };
 
 pub type StoriesResponse = Vec<usize>;
 
-pub async fn get_stories(kind: StoryType) -> Result<StoriesResponse, RequestError> {
-    let firebase = firebase();
-    let url = get_stories_url(kind);
-    let response = firebase.get(&url).await?;
+impl HackerNewsClient {
+    pub async fn get_stories(&self, kind: StoryType) -> HnResult<StoriesResponse> {
+        let url = get_stories_url(kind);
+        let response = self.cache.get(&url).await?;
+
+        if let CacheItemType::Story(res) = response {
+            Ok(res)
+        } else {
+            Err(HnError::Decode(url))
+        }
+    }
 
-    if let CacheItemType::Story(res) = response {
-        Ok(res)
-    } else {
-        Err(RequestError::SerializeError)
+    /// Last cached copy of the feed, possibly expired. Never hits the network.
+    pub async fn cached_stories(&self, kind: StoryType) -> Option<StoriesResponse> {
+        match self.cache.get_stale(&get_stories_url(kind)).await {
+            Some(CacheItemType::Story(stories)) => Some(stories),
+            _ => None,
+        }
     }
+
+    /// Drops the cached feed so that the next read fetches it again.
+    pub async fn invalidate_stories(&self, kind: StoryType) {
+        self.cache.remove(&get_stories_url(kind)).await;
+    }
+}
+
+pub async fn get_stories(kind: StoryType) -> HnResult<StoriesResponse> {
+    default_client().get_stories(kind).await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::client::fixture_client;
 
     #[tokio::test]
     async fn test_get_stories() {
-        let response = get_stories(StoryType::Top).await;
+        let response = fixture_client().get_stories(StoryType::Top).await;
         assert!(response.is_ok());
         let stories = response.unwrap();
         assert!(!stories.is_empty());
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api_url::{get_max_item_url, get_updates_url},
+    client::{HackerNewsClient, default_client},
+    error::HnResult,
+};
+
+/// Items and profiles that changed recently, as listed by `v0/updates`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Updates {
+    #[serde(default)]
+    pub items: Vec<usize>,
+    #[serde(default)]
+    pub profiles: Vec<String>,
+}
+
+impl HackerNewsClient {
+    /// Recently changed items and profiles. Always fetched from the network.
+    pub async fn get_updates(&self) -> HnResult<Updates> {
+        self.cache.fetch_live(&get_updates_url()).await
+    }
+
+    /// The largest item id so far. Always fetched from the network.
+    pub async fn get_max_item(&self) -> HnResult<usize> {
+        self.cache.fetch_live(&get_max_item_url()).await
+    }
+
+    /// Drops the cached copy of everything listed in `updates`.
+    pub async fn apply_updates(&self, updates: &Updates) {
+        for &id in &updates.items {
+            self.invalidate_item(id).await;
+        }
+        for user in &updates.profiles {
+            self.invalidate_user(user).await;
+        }
+    }
+}
+
+pub async fn get_updates() -> HnResult<Updates> {
+    default_client().get_updates().await
+}
+
+pub async fn get_max_item() -> HnResult<usize> {
+    default_client().get_max_item().await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::transport::FixtureTransport;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_apply_updates() {
+        let transport = Arc::new(
+            FixtureTransport::new()
+                .with("updates", json!({"items": [1], "profiles": ["pg"]}))
+                .with("maxitem", json!(42))
+                .with(
+                    "item/1",
+                    json!({"id": 1, "type": "story", "time": 0, "score": 1}),
+                ),
+        );
+        let client = HackerNewsClient::builder()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(client.get_max_item().await, Ok(42));
+        assert_eq!(client.get_item(1).await.unwrap().score, Some(1));
+
+        transport.insert(
+            "item/1",
+            json!({"id": 1, "type": "story", "time": 0, "score": 2}),
+        );
+        assert_eq!(client.get_item(1).await.unwrap().score, Some(1));
+
+        let updates = client.get_updates().await.unwrap();
+        assert_eq!(updates.profiles, vec!["pg".to_string()]);
+        client.apply_updates(&updates).await;
+        assert_eq!(client.get_item(1).await.unwrap().score, Some(2));
+    }
+}
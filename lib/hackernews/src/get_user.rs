@@ -1,9 +1,10 @@
-use firebase_rs::RequestError;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    api_url::{firebase, get_user_url},
+    api_url::get_user_url,
     cache::CacheItemType,
+    client::{HackerNewsClient, default_client},
+    error::{HnError, HnResult},
 };
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
@@ -15,14 +16,36 @@ pub struct User {
     pub submitted: Vec<u64>,
 }
 
-pub async fn get_user(user_name: &str) -> Result<User, RequestError> {
-    let firebase = firebase();
-    let url = get_user_url(user_name);
-    let response = firebase.get(&url).await?;
+impl HackerNewsClient {
+    pub async fn get_user(&self, user_name: &str) -> HnResult<User> {
+        let url = get_user_url(user_name);
+        let response = self.cache.get(&url).await?;
 
-    if let CacheItemType::User(user) = response {
-        Ok(user)
-    } else {
-        Err(RequestError::SerializeError)
+        if let CacheItemType::User(user) = response {
+            Ok(user)
+        } else {
+            Err(HnError::Decode(url))
+        }
+    }
+
+    /// Drops the cached profile so that the next read fetches it again.
+    pub async fn invalidate_user(&self, user_name: &str) {
+        self.cache.remove(&get_user_url(user_name)).await;
+    }
+}
+
+pub async fn get_user(user_name: &str) -> HnResult<User> {
+    default_client().get_user(user_name).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::fixture_client;
+
+    #[tokio::test]
+    async fn test_get_user() {
+        let user = fixture_client().get_user("dhouston").await.unwrap();
+        assert_eq!(user.id, "dhouston");
+        assert!(user.submitted.contains(&8863));
     }
 }
@@ -1,8 +1,24 @@
 mod api_url;
 mod cache;
+mod client;
+mod disk_cache;
+mod error;
+mod memory_cache;
+#[cfg(test)]
+mod test_util;
 
 pub mod get_items;
+pub mod get_poll;
 pub mod get_stories;
+pub mod get_updates;
 pub mod get_user;
+pub mod search;
+pub mod subscribe;
+pub mod transport;
+pub mod vcr;
 
-pub use api_url::StoryType;
+pub use api_url::{HN_ITEM_URL, StoryType};
+pub use client::{HackerNewsClient, HackerNewsClientBuilder, default_client};
+pub use disk_cache::DiskLimits;
+pub use error::{HnError, HnResult};
+pub use memory_cache::{CacheLimits, CacheStats};
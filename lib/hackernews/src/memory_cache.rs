@@ -0,0 +1,239 @@
+use std::time::{Duration, Instant};
+
+use lru::LruCache;
+
+use crate::cache::CacheItemType;
+
+/// Bounds for the in-memory tier. `None` means unbounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheLimits {
+    pub max_entries: Option<usize>,
+    pub max_bytes: Option<usize>,
+    /// How often expired entries are swept out, even if never read again.
+    pub sweep_interval: Duration,
+}
+
+impl Default for CacheLimits {
+    fn default() -> Self {
+        CacheLimits {
+            max_entries: Some(10_000),
+            max_bytes: Some(64 * 1024 * 1024),
+            sweep_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Counters for tuning [`CacheLimits`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub disk_hits: u64,
+    pub evictions: u64,
+    pub expirations: u64,
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+struct Entry {
+    value: CacheItemType,
+    expire: Instant,
+    size: usize,
+}
+
+/// LRU map that evicts the least recently read entries once either limit is
+/// exceeded.
+pub struct MemoryCache {
+    entries: LruCache<String, Entry>,
+    limits: CacheLimits,
+    stats: CacheStats,
+    last_sweep: Instant,
+}
+
+impl MemoryCache {
+    pub fn new(limits: CacheLimits) -> Self {
+        MemoryCache {
+            entries: LruCache::unbounded(),
+            limits,
+            stats: CacheStats::default(),
+            last_sweep: Instant::now(),
+        }
+    }
+
+    pub fn limits(&self) -> CacheLimits {
+        self.limits
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    pub fn record_disk_hit(&mut self) {
+        self.stats.disk_hits += 1;
+    }
+
+    /// Looks up a fresh entry and marks it as most recently used. Expired
+    /// entries are dropped and reported as a miss.
+    pub fn get(&mut self, key: &str) -> Option<CacheItemType> {
+        let now = Instant::now();
+        self.sweep_if_due(now);
+
+        match self.entries.get(key) {
+            Some(entry) if entry.expire > now => {
+                self.stats.hits += 1;
+                Some(entry.value.clone())
+            }
+            Some(_) => {
+                self.remove(key);
+                self.stats.expirations += 1;
+                self.stats.misses += 1;
+                None
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Returns the entry even if it has expired, without touching the LRU order
+    /// or the counters.
+    pub fn peek(&self, key: &str) -> Option<&CacheItemType> {
+        self.entries.peek(key).map(|entry| &entry.value)
+    }
+
+    pub fn insert(&mut self, key: String, value: CacheItemType, ttl: Duration) {
+        let size = key.len() + serde_json::to_vec(&value).map_or(0, |bytes| bytes.len());
+        let entry = Entry {
+            value,
+            expire: Instant::now() + ttl,
+            size,
+        };
+
+        if let Some(old) = self.entries.put(key, entry) {
+            self.stats.bytes -= old.size;
+        }
+        self.stats.bytes += size;
+        self.evict();
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if let Some(old) = self.entries.pop(key) {
+            self.stats.bytes -= old.size;
+        }
+    }
+
+    /// Drops every expired entry and returns how many were removed.
+    pub fn sweep_expired(&mut self) -> usize {
+        let now = Instant::now();
+        self.last_sweep = now;
+
+        let expired = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expire <= now)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in &expired {
+            self.remove(key);
+        }
+        self.stats.expirations += expired.len() as u64;
+        expired.len()
+    }
+
+    fn sweep_if_due(&mut self, now: Instant) {
+        if now.duration_since(self.last_sweep) >= self.limits.sweep_interval {
+            self.sweep_expired();
+        }
+    }
+
+    fn over_limit(&self) -> bool {
+        self.limits
+            .max_entries
+            .is_some_and(|max| self.entries.len() > max)
+            || self
+                .limits
+                .max_bytes
+                .is_some_and(|max| self.stats.bytes > max)
+    }
+
+    fn evict(&mut self) {
+        while self.over_limit() {
+            match self.entries.pop_lru() {
+                Some((_, old)) => {
+                    self.stats.bytes -= old.size;
+                    self.stats.evictions += 1;
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_entries: Option<usize>, max_bytes: Option<usize>) -> CacheLimits {
+        CacheLimits {
+            max_entries,
+            max_bytes,
+            sweep_interval: Duration::from_secs(60),
+        }
+    }
+
+    fn story(ids: Vec<usize>) -> CacheItemType {
+        CacheItemType::Story(ids)
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = MemoryCache::new(limits(Some(2), None));
+        let ttl = Duration::from_secs(60);
+        cache.insert("a".to_string(), story(vec![1]), ttl);
+        cache.insert("b".to_string(), story(vec![2]), ttl);
+
+        // Reading `a` makes `b` the eviction candidate.
+        assert!(cache.get("a").is_some());
+        cache.insert("c".to_string(), story(vec![3]), ttl);
+
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("c").is_some());
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 1);
+    }
+
+    #[test]
+    fn test_byte_budget() {
+        let mut cache = MemoryCache::new(limits(None, Some(40)));
+        let ttl = Duration::from_secs(60);
+        cache.insert("a".to_string(), story(vec![1, 2, 3]), ttl);
+        cache.insert("b".to_string(), story(vec![4, 5, 6]), ttl);
+        cache.insert("c".to_string(), story(vec![7, 8, 9]), ttl);
+
+        let stats = cache.stats();
+        assert!(stats.bytes <= 40);
+        assert!(stats.evictions >= 1);
+        assert!(cache.peek("c").is_some());
+    }
+
+    #[test]
+    fn test_sweep_expired() {
+        let mut cache = MemoryCache::new(limits(None, None));
+        cache.insert("a".to_string(), story(vec![1]), Duration::ZERO);
+        cache.insert("b".to_string(), story(vec![2]), Duration::from_secs(60));
+
+        assert_eq!(cache.sweep_expired(), 1);
+        assert!(cache.peek("a").is_none());
+        assert!(cache.peek("b").is_some());
+        assert_eq!(cache.stats().expirations, 1);
+    }
+}
@@ -0,0 +1,384 @@
+//! Full-text search through an [hn.algolia.com](https://hn.algolia.com/api)
+//! compatible backend.
+
+use std::{fmt::Display, time::Duration};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::DEFAULT_TIMEOUT,
+    error::{HnError, HnResult},
+};
+
+const SEARCH_BASE_URL: &str = "https://hn.algolia.com/api/v1/";
+
+const SEARCH_ENDPOINT: &str = "search";
+const SEARCH_BY_DATE_ENDPOINT: &str = "search_by_date";
+
+static DEFAULT_SEARCH_CLIENT: Lazy<SearchClient> = Lazy::new(SearchClient::default);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tag {
+    Story,
+    Comment,
+    AskHn,
+    ShowHn,
+    /// Items posted by this user.
+    Author(String),
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tag::Story => write!(f, "story"),
+            Tag::Comment => write!(f, "comment"),
+            Tag::AskHn => write!(f, "ask_hn"),
+            Tag::ShowHn => write!(f, "show_hn"),
+            Tag::Author(user) => write!(f, "author_{}", user),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumericField {
+    Points,
+    NumComments,
+    /// Creation time in Unix seconds.
+    CreatedAt,
+}
+
+impl Display for NumericField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let field = match self {
+            NumericField::Points => "points",
+            NumericField::NumComments => "num_comments",
+            NumericField::CreatedAt => "created_at_i",
+        };
+        write!(f, "{}", field)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Eq => "=",
+            Comparison::Ge => ">=",
+            Comparison::Gt => ">",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+/// What to search for. All tags and filters must match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub query: String,
+    pub tags: Vec<Tag>,
+    pub numeric_filters: Vec<(NumericField, Comparison, i64)>,
+    /// Zero-based page of results.
+    pub page: usize,
+    /// `None` leaves the page size to the backend.
+    pub hits_per_page: Option<usize>,
+    /// Newest first instead of most relevant first.
+    pub by_date: bool,
+}
+
+impl SearchQuery {
+    pub fn new(query: impl Into<String>) -> Self {
+        SearchQuery {
+            query: query.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn tag(mut self, tag: Tag) -> Self {
+        self.tags.push(tag);
+        self
+    }
+
+    pub fn filter(mut self, field: NumericField, op: Comparison, value: i64) -> Self {
+        self.numeric_filters.push((field, op, value));
+        self
+    }
+
+    pub fn created_after(self, time: DateTime<Utc>) -> Self {
+        self.filter(NumericField::CreatedAt, Comparison::Gt, time.timestamp())
+    }
+
+    pub fn page(mut self, page: usize) -> Self {
+        self.page = page;
+        self
+    }
+
+    pub fn hits_per_page(mut self, hits_per_page: usize) -> Self {
+        self.hits_per_page = Some(hits_per_page);
+        self
+    }
+
+    pub fn by_date(mut self) -> Self {
+        self.by_date = true;
+        self
+    }
+
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![("query", self.query.clone())];
+        if !self.tags.is_empty() {
+            let tags = self.tags.iter().map(Tag::to_string).collect::<Vec<_>>();
+            params.push(("tags", tags.join(",")));
+        }
+        if !self.numeric_filters.is_empty() {
+            let filters = self
+                .numeric_filters
+                .iter()
+                .map(|(field, op, value)| format!("{}{}{}", field, op, value))
+                .collect::<Vec<_>>();
+            params.push(("numericFilters", filters.join(",")));
+        }
+        params.push(("page", self.page.to_string()));
+        if let Some(hits_per_page) = self.hits_per_page {
+            params.push(("hitsPerPage", hits_per_page.to_string()));
+        }
+        params
+    }
+}
+
+/// A story or comment matching a search. Fields that do not apply to the
+/// kind of item are `None`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    /// The item id, as a string.
+    #[serde(rename = "objectID")]
+    pub object_id: String,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub author: Option<String>,
+    pub points: Option<usize>,
+    pub num_comments: Option<usize>,
+    pub story_text: Option<String>,
+    pub comment_text: Option<String>,
+    /// For comments, the story they belong to.
+    pub story_id: Option<usize>,
+    pub parent_id: Option<usize>,
+    /// Creation time in Unix seconds.
+    pub created_at_i: i64,
+    #[serde(rename = "_tags", default)]
+    pub tags: Vec<String>,
+}
+
+impl SearchHit {
+    /// The Hacker News item id, usable with
+    /// [`HackerNewsClient::get_item`](crate::HackerNewsClient::get_item).
+    pub fn id(&self) -> Option<usize> {
+        self.object_id.parse().ok()
+    }
+
+    pub fn created_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.created_at_i, 0).unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    /// Zero-based page these hits are from.
+    pub page: usize,
+    #[serde(rename = "nbPages")]
+    pub pages: usize,
+    #[serde(rename = "nbHits")]
+    pub total: usize,
+    #[serde(rename = "hitsPerPage")]
+    pub hits_per_page: usize,
+}
+
+impl SearchResults {
+    pub fn has_next_page(&self) -> bool {
+        self.page + 1 < self.pages
+    }
+}
+
+/// Talks to hn.algolia.com, or anything that serves the same
+/// `<base>/search` and `<base>/search_by_date` endpoints.
+#[derive(Debug, Clone)]
+pub struct SearchClient {
+    client: reqwest::Client,
+    base: Url,
+    timeout: Duration,
+}
+
+impl SearchClient {
+    pub fn new(base_url: &str) -> HnResult<Self> {
+        let base_url = if base_url.ends_with('/') {
+            base_url.to_string()
+        } else {
+            format!("{}/", base_url)
+        };
+        let base = Url::parse(&base_url).map_err(|e| HnError::InvalidUrl(e.to_string()))?;
+        if !matches!(base.scheme(), "http" | "https") {
+            return Err(HnError::InvalidUrl(format!(
+                "unsupported scheme {}",
+                base.scheme()
+            )));
+        }
+
+        Ok(SearchClient {
+            client: http_client(DEFAULT_TIMEOUT),
+            base,
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// Upper bound for a single search, including reading the body.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = http_client(timeout);
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn url(&self, query: &SearchQuery) -> HnResult<Url> {
+        let endpoint = if query.by_date {
+            SEARCH_BY_DATE_ENDPOINT
+        } else {
+            SEARCH_ENDPOINT
+        };
+        let mut url = self
+            .base
+            .join(endpoint)
+            .map_err(|_| HnError::UnknownKey(endpoint.to_string()))?;
+        url.query_pairs_mut().extend_pairs(query.params());
+        Ok(url)
+    }
+
+    pub async fn search(&self, query: &SearchQuery) -> HnResult<SearchResults> {
+        let url = self.url(query)?;
+        let key = url.path().to_string();
+        let network = |e: reqwest::Error| {
+            if e.is_timeout() {
+                HnError::Timeout(self.timeout)
+            } else {
+                HnError::Network(key.clone())
+            }
+        };
+
+        let body = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(network)?
+            .error_for_status()
+            .map_err(network)?
+            .text()
+            .await
+            .map_err(network)?;
+        serde_json::from_str(&body).map_err(|_| HnError::Decode(key.clone()))
+    }
+}
+
+/// Panics where `reqwest::Client::new` would, if TLS cannot be set up.
+fn http_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("The TLS backend can be initialized")
+}
+
+impl Default for SearchClient {
+    fn default() -> Self {
+        SearchClient::new(SEARCH_BASE_URL).expect("The HN search API URL is valid")
+    }
+}
+
+pub async fn search(query: &SearchQuery) -> HnResult<SearchResults> {
+    DEFAULT_SEARCH_CLIENT.search(query).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{Reply, serve};
+
+    const RESULTS: &str = r#"{
+        "hits": [{
+            "objectID": "8863",
+            "title": "My YC app: Dropbox",
+            "url": "http://www.getdropbox.com/u/2/screencast.html",
+            "author": "dhouston",
+            "points": 111,
+            "num_comments": 71,
+            "story_text": null,
+            "created_at_i": 1175714200,
+            "_tags": ["story", "author_dhouston", "story_8863"]
+        }],
+        "page": 0,
+        "nbPages": 3,
+        "nbHits": 60,
+        "hitsPerPage": 20
+    }"#;
+
+    #[test]
+    fn test_url() {
+        let client = SearchClient::default();
+        let query = SearchQuery::new("rust tui")
+            .tag(Tag::Story)
+            .tag(Tag::Author("pg".to_string()))
+            .filter(NumericField::Points, Comparison::Ge, 100)
+            .filter(NumericField::CreatedAt, Comparison::Gt, 1_700_000_000)
+            .page(2)
+            .hits_per_page(50)
+            .by_date();
+        assert_eq!(
+            client.url(&query).unwrap().as_str(),
+            "https://hn.algolia.com/api/v1/search_by_date?query=rust+tui&tags=story%2Cauthor_pg\
+             &numericFilters=points%3E%3D100%2Ccreated_at_i%3E1700000000&page=2&hitsPerPage=50"
+        );
+        assert!(SearchClient::new("ftp://example.com/").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let (base, mut requests) = serve(vec![Reply::Json(RESULTS)]).await;
+        let client = SearchClient::new(&format!("{}/api/v1", base)).unwrap();
+        let query = SearchQuery::new("dropbox").tag(Tag::ShowHn);
+
+        let results = client.search(&query).await.unwrap();
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            "GET /api/v1/search?query=dropbox&tags=show_hn&page=0 HTTP/1.1"
+        );
+        assert_eq!(results.total, 60);
+        assert!(results.has_next_page());
+        assert_eq!(results.hits[0].id(), Some(8863));
+        assert_eq!(results.hits[0].author.as_deref(), Some("dhouston"));
+    }
+
+    #[tokio::test]
+    async fn test_search_timeout() {
+        let (base, _) = serve(vec![Reply::Silence]).await;
+        let client = SearchClient::new(&format!("{}/api/v1", base))
+            .unwrap()
+            .with_timeout(Duration::from_millis(50));
+
+        assert_eq!(
+            client.search(&SearchQuery::new("dropbox")).await,
+            Err(HnError::Timeout(Duration::from_millis(50)))
+        );
+    }
+}
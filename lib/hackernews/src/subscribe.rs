@@ -0,0 +1,371 @@
+//! Live updates over server-sent events.
+//!
+//! Firebase streams changes to any path when asked for `text/event-stream`:
+//! a `put` with the whole value right after connecting, then a `put` or
+//! `patch` for every change, and a `keep-alive` every thirty seconds or so.
+
+use std::{collections::VecDeque, time::Duration};
+
+use futures::{
+    StreamExt,
+    stream::{self, BoxStream},
+};
+use reqwest::{Response, Url, header::ACCEPT};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    api_url::{StoryType, get_item_url, get_stories_url},
+    client::{HackerNewsClient, default_client},
+    transport::FirebaseTransport,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// `data` replaces the value at `path`, relative to the subscribed path.
+    Put {
+        path: String,
+        data: Value,
+    },
+    /// The keys of `data` replace those of the object at `path`.
+    Patch {
+        path: String,
+        data: Value,
+    },
+    KeepAlive,
+    /// The server refused the subscription. Nothing follows.
+    Cancel,
+}
+
+/// Reconnect timing for [`HackerNewsClient::subscribe_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscribeOptions {
+    /// Delay before the first reconnect, doubled after every failed one.
+    pub min_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Reconnect when nothing, not even a keep-alive, arrives for this long.
+    pub idle_timeout: Duration,
+    /// Give up on a connection attempt whose response takes this long.
+    pub connect_timeout: Duration,
+}
+
+impl Default for SubscribeOptions {
+    fn default() -> Self {
+        SubscribeOptions {
+            min_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            idle_timeout: Duration::from_secs(90),
+            connect_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl SubscribeOptions {
+    pub fn min_backoff(mut self, min_backoff: Duration) -> Self {
+        self.min_backoff = min_backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+}
+
+#[derive(Deserialize)]
+struct Payload {
+    path: String,
+    data: Value,
+}
+
+/// Splits an event stream into events. Input may be cut anywhere, even in
+/// the middle of a UTF-8 sequence.
+#[derive(Debug, Default)]
+struct EventParser {
+    buf: Vec<u8>,
+    event: String,
+    data: Vec<String>,
+}
+
+impl EventParser {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<Event> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buf.iter().position(|&b| b == b'\n') {
+            let line = self.buf.drain(..=end).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                events.extend(self.dispatch());
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = value.to_string(),
+                "data" => self.data.push(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+
+    fn dispatch(&mut self) -> Option<Event> {
+        let event = std::mem::take(&mut self.event);
+        let data = std::mem::take(&mut self.data).join("\n");
+
+        match event.as_str() {
+            "put" | "patch" => {
+                let Payload { path, data } = serde_json::from_str(&data).ok()?;
+                if event == "put" {
+                    Some(Event::Put { path, data })
+                } else {
+                    Some(Event::Patch { path, data })
+                }
+            }
+            "keep-alive" => Some(Event::KeepAlive),
+            "cancel" => Some(Event::Cancel),
+            _ => None,
+        }
+    }
+}
+
+struct Subscription {
+    client: reqwest::Client,
+    url: Url,
+    options: SubscribeOptions,
+    backoff: Duration,
+    response: Option<Response>,
+    parser: EventParser,
+    pending: VecDeque<Event>,
+    cancelled: bool,
+}
+
+impl Subscription {
+    async fn connect(&self) -> Option<Response> {
+        let request = self
+            .client
+            .get(self.url.clone())
+            .header(ACCEPT, "text/event-stream")
+            .send();
+        // A server that accepts but never answers counts as a failed attempt.
+        tokio::time::timeout(self.options.connect_timeout, request)
+            .await
+            .ok()?
+            .and_then(Response::error_for_status)
+            .ok()
+    }
+
+    async fn wait_to_reconnect(&mut self) {
+        self.response = None;
+        tokio::time::sleep(self.backoff).await;
+        self.backoff = (self.backoff * 2).min(self.options.max_backoff);
+    }
+
+    async fn next_event(&mut self) -> Option<Event> {
+        loop {
+            if self.cancelled {
+                return None;
+            }
+            if let Some(event) = self.pending.pop_front() {
+                // Receiving anything means the connection is healthy again.
+                self.backoff = self.options.min_backoff;
+                self.cancelled = event == Event::Cancel;
+                return Some(event);
+            }
+
+            let Some(response) = &mut self.response else {
+                match self.connect().await {
+                    Some(response) => {
+                        self.response = Some(response);
+                        self.parser = EventParser::default();
+                    }
+                    None => self.wait_to_reconnect().await,
+                }
+                continue;
+            };
+
+            match tokio::time::timeout(self.options.idle_timeout, response.chunk()).await {
+                Ok(Ok(Some(chunk))) => self.pending.extend(self.parser.feed(&chunk)),
+                _ => self.wait_to_reconnect().await,
+            }
+        }
+    }
+}
+
+impl FirebaseTransport {
+    /// Streams the changes to `path`, reconnecting with exponential backoff
+    /// whenever the connection drops. Ends only after [`Event::Cancel`].
+    pub(crate) fn event_stream(
+        &self,
+        path: &str,
+        options: SubscribeOptions,
+    ) -> BoxStream<'static, Event> {
+        let Ok(url) = self.url(path) else {
+            return stream::empty().boxed();
+        };
+
+        let subscription = Subscription {
+            client: self.client.clone(),
+            url,
+            options,
+            backoff: options.min_backoff,
+            response: None,
+            parser: EventParser::default(),
+            pending: VecDeque::new(),
+            cancelled: false,
+        };
+        stream::unfold(subscription, |mut subscription| async move {
+            let event = subscription.next_event().await?;
+            Some((event, subscription))
+        })
+        .boxed()
+    }
+}
+
+impl HackerNewsClient {
+    /// Streams the changes to an API path such as `topstories` or
+    /// `item/8863`. Transports without a live connection yield nothing.
+    pub fn subscribe(&self, path: &str) -> BoxStream<'static, Event> {
+        self.subscribe_with(path, SubscribeOptions::default())
+    }
+
+    pub fn subscribe_stories(&self, kind: StoryType) -> BoxStream<'static, Event> {
+        self.subscribe(&get_stories_url(kind))
+    }
+
+    pub fn subscribe_item(&self, item_id: usize) -> BoxStream<'static, Event> {
+        self.subscribe(&get_item_url(item_id))
+    }
+
+    pub fn subscribe_with(
+        &self,
+        path: &str,
+        options: SubscribeOptions,
+    ) -> BoxStream<'static, Event> {
+        self.cache.transport().subscribe(path, options)
+    }
+}
+
+pub fn subscribe(path: &str) -> BoxStream<'static, Event> {
+    default_client().subscribe(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{Reply, serve};
+    use crate::transport::Transport;
+    use serde_json::json;
+
+    /// Serves one event stream per connection, closing each after its body,
+    /// and returns the base URL.
+    async fn serve_streams(replies: Vec<Reply>) -> String {
+        let (base, _) = serve(replies).await;
+        format!("{}/v0", base)
+    }
+
+    #[test]
+    fn test_parser() {
+        let mut parser = EventParser::default();
+        assert!(
+            parser
+                .feed(b": comment\r\nevent: put\r\ndata: {\"path\"")
+                .is_empty()
+        );
+        let events = parser.feed(
+            b": \"/\", \"data\": [1, 2]}\r\n\r\nevent: keep-alive\ndata: null\n\nevent: patch\n",
+        );
+        assert_eq!(
+            events,
+            vec![
+                Event::Put {
+                    path: "/".to_string(),
+                    data: json!([1, 2])
+                },
+                Event::KeepAlive
+            ]
+        );
+
+        let events = parser.feed(b"data: {\"path\": \"/score\",\ndata: \"data\": 5}\n\n");
+        assert_eq!(
+            events,
+            vec![Event::Patch {
+                path: "/score".to_string(),
+                data: json!(5)
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_until_cancelled() {
+        let base = serve_streams(vec![
+            Reply::Events("event: put\ndata: {\"path\": \"/\", \"data\": 1}\n\n"),
+            Reply::Events(
+                "event: put\ndata: {\"path\": \"/\", \"data\": 2}\n\nevent: cancel\ndata: null\n\n",
+            ),
+        ])
+        .await;
+        let transport = FirebaseTransport::new(&base).unwrap();
+        let options = SubscribeOptions::default().min_backoff(Duration::from_millis(10));
+
+        let events = transport
+            .subscribe("topstories", options)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            events,
+            vec![
+                Event::Put {
+                    path: "/".to_string(),
+                    data: json!(1)
+                },
+                Event::Put {
+                    path: "/".to_string(),
+                    data: json!(2)
+                },
+                Event::Cancel
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_after_connect_timeout() {
+        // The first connection never gets an answer.
+        let base = serve_streams(vec![
+            Reply::Silence,
+            Reply::Events("event: cancel\ndata: null\n\n"),
+        ])
+        .await;
+        let transport = FirebaseTransport::new(&base).unwrap();
+        let options = SubscribeOptions::default()
+            .min_backoff(Duration::from_millis(10))
+            .connect_timeout(Duration::from_millis(50));
+
+        let events = tokio::time::timeout(
+            Duration::from_secs(5),
+            transport
+                .subscribe("topstories", options)
+                .collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(events, vec![Event::Cancel]);
+    }
+}
@@ -0,0 +1,73 @@
+//! Helpers shared by the tests of several modules.
+
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    sync::mpsc::{UnboundedReceiver, unbounded_channel},
+};
+
+/// The canned API responses under `fixtures/`.
+pub(crate) fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures")
+}
+
+/// A path in the system temp dir that no other test uses.
+pub(crate) fn temp_path(prefix: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    std::env::temp_dir().join(format!("{}_{}", prefix, nanos))
+}
+
+/// How [`serve`] answers a connection.
+pub(crate) enum Reply {
+    /// A JSON document.
+    Json(&'static str),
+    /// An event stream, closed after `body`.
+    Events(&'static str),
+    /// Nothing: the connection is held open without an answer.
+    Silence,
+}
+
+/// Answers one connection per reply, in order, and returns the server's
+/// `http://<addr>` along with the request line of every connection.
+pub(crate) async fn serve(replies: Vec<Reply>) -> (String, UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = unbounded_channel();
+
+    tokio::spawn(async move {
+        for reply in replies {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut buf = [0; 2048];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let _ = tx.send(request.lines().next().unwrap_or_default().to_string());
+
+                let response = match reply {
+                    Reply::Json(body) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    ),
+                    Reply::Events(body) => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n{}",
+                        body
+                    ),
+                    Reply::Silence => return std::future::pending().await,
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    (base, rx)
+}
@@ -0,0 +1,223 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use futures::{
+    StreamExt,
+    future::BoxFuture,
+    stream::{self, BoxStream},
+};
+use reqwest::Url;
+use serde_json::Value;
+
+use crate::{
+    api_url::API_BASE_URL,
+    error::{HnError, HnResult},
+    subscribe::{Event, SubscribeOptions},
+};
+
+/// Fetches the raw JSON stored at an API path such as `item/8863` or
+/// `topstories`. A `null` document is reported as [`HnError::NotFound`].
+pub trait Transport: Send + Sync {
+    fn fetch<'a>(&'a self, path: &'a str) -> BoxFuture<'a, HnResult<Value>>;
+
+    /// Streams live changes to `path`. Transports without a live
+    /// connection yield nothing.
+    fn subscribe(&self, _path: &str, _options: SubscribeOptions) -> BoxStream<'static, Event> {
+        stream::empty().boxed()
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn fetch<'a>(&'a self, path: &'a str) -> BoxFuture<'a, HnResult<Value>> {
+        (**self).fetch(path)
+    }
+
+    fn subscribe(&self, path: &str, options: SubscribeOptions) -> BoxStream<'static, Event> {
+        (**self).subscribe(path, options)
+    }
+}
+
+/// Talks to the Firebase REST API, or anything that serves the same
+/// `<base>/<path>.json` layout.
+#[derive(Debug, Clone)]
+pub struct FirebaseTransport {
+    pub(crate) client: reqwest::Client,
+    base: Url,
+}
+
+impl FirebaseTransport {
+    pub fn new(base_url: &str) -> HnResult<Self> {
+        let base_url = if base_url.ends_with('/') {
+            base_url.to_string()
+        } else {
+            format!("{}/", base_url)
+        };
+        let base = Url::parse(&base_url).map_err(|e| HnError::InvalidUrl(e.to_string()))?;
+        if !matches!(base.scheme(), "http" | "https") {
+            return Err(HnError::InvalidUrl(format!(
+                "unsupported scheme {}",
+                base.scheme()
+            )));
+        }
+
+        Ok(FirebaseTransport {
+            client: reqwest::Client::new(),
+            base,
+        })
+    }
+
+    pub fn url(&self, path: &str) -> HnResult<Url> {
+        self.base
+            .join(&format!("{}.json", path))
+            .map_err(|_| HnError::UnknownKey(path.to_string()))
+    }
+}
+
+impl Default for FirebaseTransport {
+    fn default() -> Self {
+        FirebaseTransport::new(API_BASE_URL).expect("The Hacker News API URL is valid")
+    }
+}
+
+impl Transport for FirebaseTransport {
+    fn fetch<'a>(&'a self, path: &'a str) -> BoxFuture<'a, HnResult<Value>> {
+        Box::pin(async move {
+            let network = |_| HnError::Network(path.to_string());
+            let response = self
+                .client
+                .get(self.url(path)?)
+                .send()
+                .await
+                .map_err(network)?
+                .error_for_status()
+                .map_err(network)?;
+            let body = response.text().await.map_err(network)?;
+
+            match serde_json::from_str(&body) {
+                Ok(Value::Null) => Err(HnError::NotFound(path.to_string())),
+                Ok(value) => Ok(value),
+                Err(_) => Err(HnError::Decode(path.to_string())),
+            }
+        })
+    }
+
+    fn subscribe(&self, path: &str, options: SubscribeOptions) -> BoxStream<'static, Event> {
+        self.event_stream(path, options)
+    }
+}
+
+/// Serves canned responses, either registered in memory or read from
+/// `<dir>/<path>.json`, e.g. `fixtures/item/8863.json`. Anything else is
+/// reported as not found, so no request ever leaves the process.
+#[derive(Debug, Default)]
+pub struct FixtureTransport {
+    fixtures: RwLock<HashMap<String, Value>>,
+    dir: Option<PathBuf>,
+}
+
+impl FixtureTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_dir(dir: impl Into<PathBuf>) -> Self {
+        FixtureTransport {
+            fixtures: RwLock::new(HashMap::new()),
+            dir: Some(dir.into()),
+        }
+    }
+
+    pub fn with(self, path: impl Into<String>, value: Value) -> Self {
+        self.insert(path, value);
+        self
+    }
+
+    /// Registers or replaces the response for `path`.
+    pub fn insert(&self, path: impl Into<String>, value: Value) {
+        self.fixtures
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path.into(), value);
+    }
+
+    fn lookup(&self, path: &str) -> HnResult<Value> {
+        let fixtures = self.fixtures.read().unwrap_or_else(|e| e.into_inner());
+        if let Some(value) = fixtures.get(path) {
+            return Ok(value.clone());
+        }
+
+        let file = self
+            .dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", path)))
+            .ok_or_else(|| HnError::NotFound(path.to_string()))?;
+        let bytes = std::fs::read(file).map_err(|_| HnError::NotFound(path.to_string()))?;
+        serde_json::from_slice(&bytes).map_err(|_| HnError::Decode(path.to_string()))
+    }
+}
+
+impl Transport for FixtureTransport {
+    fn fetch<'a>(&'a self, path: &'a str) -> BoxFuture<'a, HnResult<Value>> {
+        let res = self.lookup(path).and_then(|value| match value {
+            Value::Null => Err(HnError::NotFound(path.to_string())),
+            value => Ok(value),
+        });
+        Box::pin(async move { res })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{Reply, fixtures_dir, serve};
+    use serde_json::json;
+
+    /// Answers a single request with `body` and returns the base URL.
+    async fn serve_once(body: &'static str) -> String {
+        let (base, _) = serve(vec![Reply::Json(body)]).await;
+        format!("{}/v0", base)
+    }
+
+    #[test]
+    fn test_url() {
+        let transport = FirebaseTransport::new("https://hacker-news.firebaseio.com/v0").unwrap();
+        assert_eq!(
+            transport.url("item/8863").unwrap().as_str(),
+            "https://hacker-news.firebaseio.com/v0/item/8863.json"
+        );
+        assert!(FirebaseTransport::new("ftp://example.com/").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_firebase_transport() {
+        let transport = FirebaseTransport::new(&serve_once("[1,2,3]").await).unwrap();
+        assert_eq!(transport.fetch("topstories").await, Ok(json!([1, 2, 3])));
+
+        let transport = FirebaseTransport::new(&serve_once("null").await).unwrap();
+        assert_eq!(
+            transport.fetch("item/0").await,
+            Err(HnError::NotFound("item/0".to_string()))
+        );
+
+        let transport = FirebaseTransport::new(&serve_once("<html>").await).unwrap();
+        assert_eq!(
+            transport.fetch("item/1").await,
+            Err(HnError::Decode("item/1".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fixture_transport() {
+        let transport = FixtureTransport::from_dir(fixtures_dir()).with("maxitem", json!(42));
+
+        assert_eq!(transport.fetch("maxitem").await, Ok(json!(42)));
+        assert!(transport.fetch("item/8863").await.is_ok());
+        assert_eq!(
+            transport.fetch("item/1").await,
+            Err(HnError::NotFound("item/1".to_string()))
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! Record-and-replay of API traffic.
+//!
+//! A cassette is a JSON lines file with one `{"path": ..., "body": ...}`
+//! object per response. [`RecordingTransport`] appends to it while talking to
+//! a real transport, and [`FixtureTransport::from_cassette`] serves it back
+//! without touching the network.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Result, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use futures::{future::BoxFuture, stream::BoxStream};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    error::{HnError, HnResult},
+    subscribe::{Event, SubscribeOptions},
+    transport::{FixtureTransport, Transport},
+};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CassetteEntry {
+    path: String,
+    body: Value,
+}
+
+/// Forwards every fetch to `inner` and writes the response to a cassette.
+/// Missing items are recorded as `null` so that replay reports them as not
+/// found too; other failures are not recorded.
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    cassette: Mutex<File>,
+}
+
+impl RecordingTransport {
+    /// Starts a new cassette at `path`, replacing any existing file.
+    pub fn new(inner: impl Transport + 'static, path: impl AsRef<Path>) -> Result<Self> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        Ok(RecordingTransport {
+            inner: Arc::new(inner),
+            cassette: Mutex::new(File::create(path)?),
+        })
+    }
+
+    fn record(&self, path: &str, body: Value) {
+        let entry = CassetteEntry {
+            path: path.to_string(),
+            body,
+        };
+        let Ok(line) = serde_json::to_string(&entry) else {
+            return;
+        };
+
+        let mut cassette = self.cassette.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(cassette, "{}", line);
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn fetch<'a>(&'a self, path: &'a str) -> BoxFuture<'a, HnResult<Value>> {
+        Box::pin(async move {
+            let res = self.inner.fetch(path).await;
+            match &res {
+                Ok(value) => self.record(path, value.clone()),
+                Err(HnError::NotFound(_)) => self.record(path, Value::Null),
+                Err(_) => {}
+            }
+            res
+        })
+    }
+
+    /// Live events are passed through but not recorded.
+    fn subscribe(&self, path: &str, options: SubscribeOptions) -> BoxStream<'static, Event> {
+        self.inner.subscribe(path, options)
+    }
+}
+
+impl FixtureTransport {
+    /// Loads a cassette written by [`RecordingTransport`]. When a path was
+    /// recorded more than once, the last response wins.
+    pub fn from_cassette(path: impl AsRef<Path>) -> Result<Self> {
+        let transport = FixtureTransport::new();
+        let reader = BufReader::new(File::open(path)?);
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: CassetteEntry = serde_json::from_str(&line)?;
+            transport.insert(entry.path, entry.body);
+        }
+
+        Ok(transport)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_path;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let cassette = temp_path("hackernews_cassette_test").with_extension("jsonl");
+        let upstream = FixtureTransport::new()
+            .with("topstories", json!([1, 2]))
+            .with("item/1", json!({"id": 1, "type": "story", "time": 0}));
+
+        let recorder = RecordingTransport::new(upstream, &cassette).unwrap();
+        recorder.fetch("topstories").await.unwrap();
+        recorder.fetch("item/1").await.unwrap();
+        assert!(recorder.fetch("item/2").await.is_err());
+        drop(recorder);
+
+        let replay = FixtureTransport::from_cassette(&cassette).unwrap();
+        assert_eq!(replay.fetch("topstories").await, Ok(json!([1, 2])));
+        assert!(replay.fetch("item/1").await.is_ok());
+        assert_eq!(
+            replay.fetch("item/2").await,
+            Err(HnError::NotFound("item/2".to_string()))
+        );
+        assert_eq!(
+            replay.fetch("newstories").await,
+            Err(HnError::NotFound("newstories".to_string()))
+        );
+
+        let _ = std::fs::remove_file(cassette);
+    }
+}
@@ -1,31 +1,41 @@
-use crossbeam_channel::Sender;
+use std::time::Instant;
+
+use hackernews::get_items::ItemResponse;
 use ratatui::{
     Frame,
-    crossterm::event::{Event, KeyCode},
-    layout::Layout,
+    crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers},
+    layout::{Constraint, Layout},
 };
+use tokio::sync::mpsc::UnboundedSender;
 
-use crate::components::{Component, DrawableComponent};
-use crate::panels::{Article, ListBlock};
-use crate::{AppAction, AppData};
+use crate::components::{Component, DrawableComponent, StatusBar};
+use crate::panels::{Article, ListBlock, Profile, RowFormat};
+use crate::storages::HistoryLimits;
+use crate::{AppAction, AppData, AppError};
 
 pub struct Environment {
-    pub tx_action: Sender<AppAction>,
+    pub tx_action: UnboundedSender<AppAction>,
+    pub tx_error: UnboundedSender<AppError>,
 }
 
 pub struct App {
     article: Article,
     list_block: ListBlock,
+    /// Shown in place of the article while open.
+    profile: Option<Profile>,
+    status_bar: StatusBar,
     focus: isize,
-    tx_action: Sender<AppAction>,
+    tx_action: UnboundedSender<AppAction>,
+    tx_error: UnboundedSender<AppError>,
     dirty: bool,
     pub is_running: bool,
 }
 
 impl App {
-    pub fn new(tx_action: Sender<AppAction>) -> Self {
+    pub fn new(tx_action: UnboundedSender<AppAction>, tx_error: UnboundedSender<AppError>) -> Self {
         let env = Environment {
             tx_action: tx_action.clone(),
+            tx_error: tx_error.clone(),
         };
 
         Self {
@@ -33,13 +43,32 @@ impl App {
             is_running: true,
             list_block: ListBlock::new(&env, true),
             article: Article::new(&env),
+            profile: None,
+            status_bar: StatusBar::new(),
             tx_action,
+            tx_error,
             focus: 0,
         }
     }
 
+    pub fn set_row_format(&mut self, row_format: RowFormat) {
+        self.list_block.set_row_format(row_format);
+    }
+
+    pub fn set_history_limits(&mut self, limits: HistoryLimits) {
+        if let Err(e) = self.list_block.set_history_limits(limits) {
+            self.show_error(AppError::new(format!("Could not save read history: {}", e)));
+        }
+    }
+
+    /// Whether a spinner is showing, so the screen needs redrawing even
+    /// without new input or data.
+    pub fn is_loading(&self) -> bool {
+        self.list_block.is_loading() || self.profile.as_ref().is_some_and(Profile::is_loading)
+    }
+
     pub fn should_draw(&mut self) -> bool {
-        if self.list_block.is_loading() {
+        if self.is_loading() {
             return true;
         }
         if self.dirty {
@@ -51,30 +80,231 @@ impl App {
     }
 
     pub fn update_data(&mut self, data: AppData) {
-        if let AppData::Story(items) = &data {
-            if let Some(items) = items {
-                if items.last().is_some() && items.last() == self.list_block.data.last() {
-                    return;
-                }
-                self.list_block.set_data(items.to_vec());
-                self.article.set_data(
-                    self.list_block
-                        .data
-                        .get(self.list_block.selected as usize)
-                        .cloned(),
-                );
+        // A feed that was on its way when a collection was opened.
+        if let AppData::Story(ids) = &data
+            && !self.list_block.is_showing_collection()
+        {
+            if let Some(ids) = ids {
+                self.list_block.set_ids(ids.to_vec());
+                self.sync_article();
             } else {
                 self.list_block.reset();
                 self.article.set_data(None);
             }
         }
+        if let AppData::StoryItems(items) = &data {
+            self.list_block.add_items(items.clone());
+            self.sync_article();
+        }
+        if let AppData::Item(item) = &data {
+            self.list_block.add_items(vec![(**item).clone()]);
+            if self.article.data.as_ref().map(|open| open.id) == Some(item.id) {
+                self.article.set_data(Some((**item).clone()));
+            }
+        }
+        if let AppData::Search {
+            query,
+            page,
+            ids,
+            items,
+            has_more,
+        } = &data
+        {
+            self.list_block
+                .set_search_results(query, *page, ids.clone(), items.clone(), *has_more);
+            self.sync_article();
+        }
+        if let Some(profile) = &mut self.profile {
+            match &data {
+                AppData::User(user) => profile.set_user(user.clone()),
+                AppData::Submitted { user, items } => profile.add_submitted(user, items.clone()),
+                _ => {}
+            }
+        }
+        if let AppData::Updates { items, stories } = &data {
+            let topic = self.list_block.topic;
+            let list_changed = self.list_block.contains_any(items);
+            let searching = self.list_block.search_query().is_some();
+            let collection = self.list_block.is_showing_collection();
+            if !searching && !collection && (list_changed || stories.contains(&topic)) {
+                self.list_block.invalidate(items);
+                let _ = self
+                    .tx_action
+                    .send(AppAction::Refresh(Box::new(AppAction::Story(topic))));
+            }
+        }
+        let is_comment = matches!(data, AppData::Comment(_));
+        let comments_loaded = matches!(data, AppData::CommentsLoaded);
         self.article.update_data(data);
+        if is_comment
+            && self.article.comments_focused()
+            && let (Some(story), Some(max_id)) = (&self.article.data, self.article.max_comment_id())
+        {
+            self.list_block.see_comments(story.id, max_id);
+        }
+        if comments_loaded {
+            self.save_seen_comments();
+        }
+        self.dirty = true;
+    }
+
+    pub fn show_error(&mut self, error: AppError) {
+        // The request the spinner was waiting for is not coming.
+        match &error.retry {
+            Some(AppAction::Story(_) | AppAction::Search { .. }) => self.list_block.stop_loading(),
+            Some(AppAction::User(_) | AppAction::Submitted { .. }) => {
+                if let Some(profile) = &mut self.profile {
+                    profile.stop_loading();
+                }
+            }
+            _ => {}
+        }
+        self.status_bar.push(error, Instant::now());
         self.dirty = true;
     }
 
+    /// When the status bar next needs redrawing to drop an expired message.
+    pub fn status_deadline(&self) -> Option<Instant> {
+        self.status_bar.deadline()
+    }
+
+    pub fn expire_status(&mut self) {
+        if self.status_bar.expire(Instant::now()) {
+            self.dirty = true;
+        }
+    }
+
+    /// Handles the status bar keys. Returns whether the key was used.
+    fn handle_status_event(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('x') if !self.status_bar.is_empty() => self.status_bar.dismiss(),
+            KeyCode::Char('R') => match self.status_bar.take_retry() {
+                Some(action) => {
+                    if matches!(action, AppAction::Story(_) | AppAction::Search { .. }) {
+                        self.list_block.start_loading();
+                    }
+                    let _ = self.tx_action.send(AppAction::Refresh(Box::new(action)));
+                }
+                None => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// Highlights what is new in the comments being opened, and remembers
+    /// this visit for next time.
+    fn visit_thread(&mut self) {
+        let Some(story) = self.article.data.clone() else {
+            return;
+        };
+        match self.list_block.visit_thread(&story) {
+            Ok(previous) => self.article.set_last_visit(previous),
+            Err(e) => self.show_error(AppError::new(format!("Could not save the visit: {}", e))),
+        }
+    }
+
+    /// Writes the comments seen while the thread was open, once it is
+    /// loaded or closed.
+    pub fn save_seen_comments(&mut self) {
+        if let Err(e) = self.list_block.save_seen_comments() {
+            self.show_error(AppError::new(format!("Could not save the visit: {}", e)));
+        }
+    }
+
+    /// Saves the selected or open story for later, or forgets it.
+    fn toggle_bookmark(&mut self) {
+        let story = if self.focus == 0 {
+            self.list_block.selected_item().cloned()
+        } else {
+            self.article.data.clone()
+        };
+        let Some(story) = story else {
+            return;
+        };
+        if let Err(e) = self.list_block.toggle_bookmark(&story) {
+            self.show_error(AppError::new(format!("Could not save the story: {}", e)));
+        }
+    }
+
+    /// Shows the selected story in the article panel, unless the reader has
+    /// moved on to something else, e.g. an item opened from a profile.
+    fn sync_article(&mut self) {
+        let selected = self.list_block.selected_item().cloned();
+        let same_story =
+            self.article.data.as_ref().map(|item| item.id) == selected.as_ref().map(|item| item.id);
+        if self.focus == 0 || same_story {
+            self.article.set_data(selected);
+        }
+    }
+
+    fn open_profile(&mut self, user_id: String) {
+        let env = Environment {
+            tx_action: self.tx_action.clone(),
+            tx_error: self.tx_error.clone(),
+        };
+        let _ = self.tx_action.send(AppAction::User(user_id.clone()));
+        self.profile = Some(Profile::new(&env, user_id));
+        self.focus = 2;
+        self.article.focus = false;
+    }
+
+    /// Closes the profile, opening `item` in the article panel if given.
+    fn close_profile(&mut self, item: Option<ItemResponse>) {
+        self.profile = None;
+        self.focus = 1;
+        if let Some(item) = item {
+            let _ = self.tx_action.send(AppAction::Watch(Some(item.id)));
+            self.article.set_data(Some(item));
+        }
+        self.article.focus = !self.article.comments_focused();
+    }
+
+    fn handle_profile_event(&mut self, key: KeyEvent) {
+        let Some(profile) = &mut self.profile else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('h') | KeyCode::Esc => self.close_profile(None),
+            KeyCode::Char('l') | KeyCode::Enter => {
+                let item = profile.selected_item().cloned();
+                if item.is_some() {
+                    self.close_profile(item);
+                }
+            }
+            KeyCode::Char('q') => self.is_running = false,
+            _ => profile.event(key),
+        }
+    }
+
     pub fn handle_event(&mut self, ev: Event) {
         if let Event::Key(key) = ev {
             self.dirty = true;
+            // The search prompt takes every key until it is closed.
+            if self.focus == 0 && self.list_block.is_editing() {
+                self.list_block.event(key);
+                return;
+            }
+            if self.handle_status_event(key) {
+                return;
+            }
+            if self.focus == 2 {
+                self.handle_profile_event(key);
+                return;
+            }
+            if self.focus == 1
+                && key.code == KeyCode::Char('u')
+                && let Some(author) = self.article.author()
+            {
+                self.open_profile(author);
+                return;
+            }
+            // `C-b` pages the list up.
+            if key.code == KeyCode::Char('b') && key.modifiers == KeyModifiers::NONE {
+                self.toggle_bookmark();
+                return;
+            }
+
             let switch_to_left_block =
                 (key.code == KeyCode::Char('h') || key.code == KeyCode::Esc) && self.article.focus;
             let switch_to_right_block = (key.code == KeyCode::Char('l')
@@ -82,33 +312,48 @@ impl App {
                 && self.list_block.focus;
 
             if self.focus == 0 {
+                let topic = self.list_block.topic;
+                let off_topic = self.list_block.search_query().is_some()
+                    || self.list_block.is_showing_collection();
                 self.list_block.event(key);
 
-                self.article.set_data(
-                    self.list_block
-                        .data
-                        .get(self.list_block.selected as usize)
-                        .cloned(),
-                );
-                self.tx_action
-                    .send(AppAction::Story(self.list_block.topic))
-                    .unwrap();
+                self.article
+                    .set_data(self.list_block.selected_item().cloned());
+                // Only a new topic, or leaving the search results or the
+                // collections, needs a new list.
+                let on_topic = self.list_block.search_query().is_none()
+                    && !self.list_block.is_showing_collection();
+                let back_to_topic = off_topic && on_topic;
+                if on_topic && (self.list_block.topic != topic || back_to_topic) {
+                    let _ = self.tx_action.send(AppAction::Story(self.list_block.topic));
+                }
             } else if self.focus == 1 {
+                let comments_focused = self.article.comments_focused();
                 self.article.event(key);
+                if !comments_focused && self.article.comments_focused() {
+                    self.visit_thread();
+                } else if comments_focused && !self.article.comments_focused() {
+                    self.save_seen_comments();
+                }
             }
             if switch_to_left_block {
+                let _ = self.tx_action.send(AppAction::Watch(None));
                 self.focus = 0;
                 self.list_block.focus = true;
                 self.article.focus = false;
             } else if switch_to_right_block {
-                if self.list_block.data.is_empty() {
+                if self.list_block.selected_item().is_none() {
                     return;
                 }
-                if self.list_block.set_read().is_ok() {
-                    self.focus = 1;
-                    self.list_block.focus = false;
-                    self.article.focus = true;
+                if let Err(e) = self.list_block.set_read() {
+                    self.show_error(AppError::new(format!("Could not save read history: {}", e)));
+                }
+                if let Some(item) = &self.article.data {
+                    let _ = self.tx_action.send(AppAction::Watch(Some(item.id)));
                 }
+                self.focus = 1;
+                self.list_block.focus = false;
+                self.article.focus = true;
             } else if key.code == KeyCode::Char('q') {
                 self.is_running = false
             }
@@ -131,11 +376,69 @@ impl App {
                 ]
             }
         });
-        let [left, right] = horizontal.areas(f.area());
+        let area = if self.status_bar.is_empty() {
+            f.area()
+        } else {
+            let [main, status] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(f.area());
+            self.status_bar.draw(f, status)?;
+            main
+        };
+        let [left, right] = horizontal.areas(area);
 
+        self.article.saved = self
+            .article
+            .data
+            .as_ref()
+            .is_some_and(|item| self.list_block.is_saved(item.id));
         self.list_block.draw(f, left)?;
-        self.article.draw(f, right)?;
+        match &mut self.profile {
+            Some(profile) => profile.draw(f, right)?,
+            None => self.article.draw(f, right)?,
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use hackernews::get_items::ItemResponse;
+    use ratatui::{Terminal, backend::TestBackend};
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+
+    #[test]
+    fn test_ctrl_b_pages_up() {
+        let (tx_action, _rx_action) = unbounded_channel();
+        let (tx_error, _rx_error) = unbounded_channel();
+        let mut app = App::new(tx_action, tx_error);
+        let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+        let press = |app: &mut App, code, modifiers| {
+            app.handle_event(Event::Key(KeyEvent::new(code, modifiers)));
+        };
+
+        app.update_data(AppData::Story(Some((1..=100).collect())));
+        let stories = (1..=100).map(|id| ItemResponse {
+            id,
+            ..ItemResponse::default()
+        });
+        app.update_data(AppData::StoryItems(stories.collect()));
+        terminal.draw(|f| app.draw(f).unwrap()).unwrap();
+        for _ in 0..40 {
+            press(&mut app, KeyCode::Char('j'), KeyModifiers::NONE);
+        }
+
+        press(&mut app, KeyCode::Char('b'), KeyModifiers::CONTROL);
+        let selected = app.list_block.selected_item().unwrap().id;
+        assert!(selected < 41);
+        assert!(!app.list_block.is_saved(41));
+        assert!(!app.list_block.is_saved(selected));
+
+        press(&mut app, KeyCode::Char('b'), KeyModifiers::NONE);
+        assert!(app.list_block.is_saved(selected));
+
+        app.list_block.remove_storage();
+    }
+}
@@ -1,6 +1,8 @@
 mod component;
 mod loading;
+mod status_bar;
 
 pub use component::DrawableComponent;
 pub use component::Component;
 pub use loading::Loading;
+pub use status_bar::StatusBar;
@@ -0,0 +1,134 @@
+use std::{
+    io::Result,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+use crate::{AppAction, AppError, components::DrawableComponent};
+
+/// How long a message stays up unless dismissed.
+const MESSAGE_TTL: Duration = Duration::from_secs(8);
+
+/// Shows errors at the bottom of the screen, newest first, until they expire
+/// or are dismissed.
+#[derive(Debug, Default)]
+pub struct StatusBar {
+    messages: Vec<(AppError, Instant)>,
+}
+
+impl StatusBar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, error: AppError, now: Instant) {
+        // The same failure again only renews the message.
+        self.messages.retain(|(shown, _)| *shown != error);
+        self.messages.push((error, now));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// The message being shown.
+    pub fn current(&self) -> Option<&AppError> {
+        self.messages.last().map(|(error, _)| error)
+    }
+
+    /// Hides the message being shown.
+    pub fn dismiss(&mut self) {
+        self.messages.pop();
+    }
+
+    /// Hides the message being shown and returns the action to retry, if it
+    /// has one.
+    pub fn take_retry(&mut self) -> Option<AppAction> {
+        self.current()?.retry.as_ref()?;
+        self.messages.pop().and_then(|(error, _)| error.retry)
+    }
+
+    /// Drops expired messages. Returns whether any were dropped.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let count = self.messages.len();
+        self.messages
+            .retain(|(_, shown_at)| now.duration_since(*shown_at) < MESSAGE_TTL);
+        self.messages.len() != count
+    }
+
+    /// When the next message expires.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.messages
+            .iter()
+            .map(|(_, shown_at)| *shown_at + MESSAGE_TTL)
+            .min()
+    }
+}
+
+impl DrawableComponent for StatusBar {
+    fn draw(&mut self, f: &mut Frame, rect: Rect) -> Result<()> {
+        let Some(error) = self.current() else {
+            return Ok(());
+        };
+
+        let mut spans = vec![Span::styled(
+            format!(" {} ", error.message),
+            Style::default().fg(Color::White).bg(Color::Red),
+        )];
+        if self.messages.len() > 1 {
+            spans.push(Span::raw(format!(" (+{})", self.messages.len() - 1)));
+        }
+        if error.retry.is_some() {
+            spans.push(Span::styled(" R", Style::default().fg(Color::Red)));
+            spans.push(Span::raw(" retry"));
+        }
+        spans.push(Span::styled(" x", Style::default().fg(Color::Red)));
+        spans.push(Span::raw(" dismiss"));
+        f.render_widget(Paragraph::new(Line::from(spans)), rect);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_expire_and_retry() {
+        let now = Instant::now();
+        let mut status = StatusBar::new();
+        status.push(
+            AppError::new("Could not load pg").retry(AppAction::User("pg".to_string())),
+            now,
+        );
+        status.push(
+            AppError::new("Could not open link"),
+            now + Duration::from_secs(5),
+        );
+        assert_eq!(status.deadline(), Some(now + MESSAGE_TTL));
+
+        // The newest message has nothing to retry, so it stays up.
+        assert_eq!(status.take_retry(), None);
+        assert_eq!(status.current().unwrap().message, "Could not open link");
+
+        assert!(status.expire(now + MESSAGE_TTL));
+        assert_eq!(status.current().unwrap().message, "Could not open link");
+        status.dismiss();
+        assert!(status.is_empty());
+
+        status.push(
+            AppError::new("Could not load pg").retry(AppAction::User("pg".to_string())),
+            now,
+        );
+        assert_eq!(status.take_retry(), Some(AppAction::User("pg".to_string())));
+        assert!(status.is_empty());
+    }
+}
@@ -1,163 +1,305 @@
 use std::time::Duration;
 
-use crossbeam_channel::unbounded;
-use futures::future::join_all;
+use crossterm::event::EventStream;
+use directories::BaseDirs;
+use futures::StreamExt;
 use hackernews::{
-    StoryType,
-    get_items::{ItemResponse, get_item},
-    get_stories::get_stories,
+    HackerNewsClient, StoryType,
+    get_items::ItemResponse,
+    get_poll::Poll,
+    get_user::User,
+    search::SearchClient,
+    transport::{FirebaseTransport, FixtureTransport},
+    vcr::RecordingTransport,
+};
+use ratatui::DefaultTerminal;
+use tokio::{
+    sync::mpsc::unbounded_channel,
+    time::{Instant, MissedTickBehavior, sleep_until},
 };
-use ratatui::{DefaultTerminal, crossterm::event};
-use tokio::{task::JoinHandle, time::sleep};
 
-use crate::app::App;
+use crate::{
+    app::App,
+    panels::RowFormat,
+    storages::{Bookmarks, HistoryLimits, bookmarks},
+    worker::Worker,
+};
 
 mod app;
 mod components;
 mod panels;
 mod storages;
+mod worker;
+
+/// How often the loading spinner moves.
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+const USAGE: &str = "Usage: hn-rs [export-bookmarks <file> | import-bookmarks <file>]";
 
 #[derive(PartialEq, Debug, Clone)]
 enum AppAction {
     Story(StoryType),
+    /// Fetches these rows of the story list.
+    StoryItems(Vec<usize>),
     Items(Vec<usize>),
+    /// Reloads a single item, e.g. the open story.
+    Item(usize),
+    Poll(usize),
+    /// Runs a full-text search for stories and loads the given result page.
+    Search {
+        query: String,
+        page: usize,
+    },
+    /// Loads a user profile.
+    User(String),
+    /// Loads a page of the items a user submitted.
+    Submitted {
+        user: String,
+        ids: Vec<usize>,
+    },
+    /// Follows live changes to the story open in the article panel, or stops
+    /// following them once no story is open.
+    Watch(Option<usize>),
+    /// Runs the wrapped action again, skipping the cached preview.
+    Refresh(Box<AppAction>),
+    /// Drops the cached copies of what the wrapped action loads, comment
+    /// threads included, and runs it again.
+    Reload(Box<AppAction>),
 }
 
 #[derive(PartialEq, Debug, Clone)]
 enum AppData {
-    Story(Option<Vec<ItemResponse>>),
-    Comment(Option<ItemResponse>),
+    /// The ids of a feed, in rank order.
+    Story(Option<Vec<usize>>),
+    /// Stories of the current feed, as they are fetched.
+    StoryItems(Vec<ItemResponse>),
+    Comment(Option<Box<ItemResponse>>),
+    /// Every comment thread asked for has been loaded, or failed to.
+    CommentsLoaded,
+    /// A fresh copy of a single item.
+    Item(Box<ItemResponse>),
+    Poll(Box<Poll>),
+    /// A page of search results, resolved to full items. `ids` lists every
+    /// hit in rank order, including those missing from `items` because they
+    /// failed to load.
+    Search {
+        query: String,
+        page: usize,
+        ids: Vec<usize>,
+        items: Vec<ItemResponse>,
+        has_more: bool,
+    },
+    User(User),
+    Submitted {
+        user: String,
+        items: Vec<ItemResponse>,
+    },
+    /// Items and feeds that changed upstream.
+    Updates {
+        items: Vec<usize>,
+        stories: Vec<StoryType>,
+    },
 }
 
-async fn fetch_tree_item(item_id: usize) -> Option<ItemResponse> {
-    let mut item = match get_item(item_id).await {
-        Ok(it) => it,
-        Err(_) => return None,
-    };
+/// Something that went wrong, shown in the status bar.
+#[derive(PartialEq, Debug, Clone)]
+struct AppError {
+    message: String,
+    /// What to run again when the reader asks for a retry.
+    retry: Option<AppAction>,
+}
 
-    if let Some(kids) = &item.kids {
-        let futures = kids
-            .iter()
-            .map(|&kid_id| async move { fetch_tree_item(kid_id).await });
-
-        item.children = Some(
-            join_all(futures)
-                .await
-                .into_iter()
-                .flatten()
-                .collect::<Vec<_>>(),
-        );
-    } else {
-        item.children = None;
+impl AppError {
+    fn new(message: impl Into<String>) -> Self {
+        AppError {
+            message: message.into(),
+            retry: None,
+        }
     }
 
-    Some(item)
+    fn retry(mut self, action: AppAction) -> Self {
+        self.retry = Some(action);
+        self
+    }
+}
+
+/// Builds the API client. The environment can swap the network for
+/// offline sources:
+/// - `HN_RS_FIXTURES=<dir>` serves `<dir>/<path>.json` files,
+/// - `HN_RS_REPLAY=<file>` serves a cassette recorded earlier,
+/// - `HN_RS_RECORD=<file>` records every response into a cassette.
+fn build_client() -> Result<HackerNewsClient, Box<dyn std::error::Error>> {
+    let builder = HackerNewsClient::builder();
+
+    let builder = if let Some(dir) = std::env::var_os("HN_RS_FIXTURES") {
+        builder.transport(FixtureTransport::from_dir(dir))
+    } else if let Some(cassette) = std::env::var_os("HN_RS_REPLAY") {
+        builder.transport(FixtureTransport::from_cassette(cassette)?)
+    } else if let Some(cassette) = std::env::var_os("HN_RS_RECORD") {
+        // No disk cache here: responses it serves would be missing from the cassette.
+        builder.transport(RecordingTransport::new(
+            FirebaseTransport::default(),
+            cassette,
+        )?)
+    } else {
+        match BaseDirs::new() {
+            Some(dirs) => builder.disk_cache_dir(dirs.cache_dir().join("hn-rs").join("api")),
+            None => builder,
+        }
+    };
+
+    Ok(builder.build()?)
+}
+
+/// `HN_RS_SEARCH_URL` points search at another hn.algolia.com compatible
+/// backend, e.g. a local stand-in.
+fn build_search_client() -> Result<SearchClient, Box<dyn std::error::Error>> {
+    match std::env::var("HN_RS_SEARCH_URL") {
+        Ok(url) => Ok(SearchClient::new(&url)?),
+        Err(_) => Ok(SearchClient::default()),
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if !args.is_empty() {
+        return bookmarks_command(&args);
+    }
+
+    let client = build_client()?;
+    let search = build_search_client()?;
+    let row_format = build_row_format()?;
+    let history_limits = build_history_limits()?;
     let mut terminal = ratatui::init();
-    run(&mut terminal)?;
+    let res = run(&mut terminal, client, search, row_format, history_limits).await;
     ratatui::restore();
 
-    Ok(())
+    res
 }
 
-fn run(terminal: &mut DefaultTerminal) -> Result<(), Box<dyn std::error::Error>> {
-    let (tx_action, rx_action) = unbounded();
-    let (tx_data, rx_data) = unbounded();
-
-    let mut app = App::new(tx_action.clone());
-
-    tokio::spawn(async move {
-        let mut last_topic: Option<StoryType> = None;
-        let mut last_item: Option<Vec<usize>> = None;
-        let mut story_handle: Option<JoinHandle<()>> = None;
-        let mut comment_handle: Option<JoinHandle<()>> = None;
-
-        loop {
-            if let Ok(rx_topic) = rx_action.try_recv() {
-                match rx_topic {
-                    AppAction::Story(topic) => {
-                        if Some(topic) == last_topic {
-                            sleep(Duration::from_millis(300)).await;
-                            continue;
-                        }
-
-                        if let Some(handle) = story_handle.take() {
-                            handle.abort();
-                        }
-
-                        last_topic = Some(topic);
-
-                        let tx_data = tx_data.clone();
-                        let topic_copy = topic;
-                        story_handle = Some(tokio::spawn(async move {
-                            let list = get_stories(topic_copy).await.unwrap_or_default();
-                            let responses = join_all(list.iter().map(|&id| get_item(id)))
-                                .await
-                                .into_iter()
-                                .filter_map(Result::ok)
-                                .collect::<Vec<_>>();
-
-                            let _ = tx_data.send(AppData::Story(Some(responses)));
-                        }));
-                    }
-
-                    AppAction::Items(items) => {
-                        if Some(items.clone()) == last_item {
-                            sleep(Duration::from_millis(300)).await;
-                            continue;
-                        }
-
-                        if let Some(handle) = comment_handle.take() {
-                            handle.abort();
-                        }
-
-                        last_item = Some(items.clone());
-
-                        if !items.is_empty() {
-                            let tx_data = tx_data.clone();
-                            let items_copy = items.clone();
-                            comment_handle = Some(tokio::spawn(async move {
-                                for &id in &items_copy {
-                                    if let Some(item) = fetch_tree_item(id).await {
-                                        let _ = tx_data.send(AppData::Comment(Some(item)));
-                                    }
-                                }
-                            }));
-                        }
-                    }
-                }
-            }
+/// `export-bookmarks <file>` and `import-bookmarks <file>` copy the saved
+/// stories out and in, as Netscape bookmark HTML for `.html` files and as
+/// JSON otherwise. Stories that are saved already are not imported again.
+fn bookmarks_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let [command, path] = args else {
+        return Err(USAGE.into());
+    };
+    let html = path.ends_with(".html") || path.ends_with(".htm");
+    let mut saved = Bookmarks::new()?;
 
-            sleep(Duration::from_millis(300)).await;
+    match command.as_str() {
+        "export-bookmarks" => {
+            let exported = if html {
+                bookmarks::to_netscape_html(saved.all())
+            } else {
+                bookmarks::to_json(saved.all())?
+            };
+            std::fs::write(path, exported)?;
+            println!("Exported {} saved stories to {}", saved.all().len(), path);
         }
-    });
+        "import-bookmarks" => {
+            let contents = std::fs::read_to_string(path)?;
+            let found = if html {
+                bookmarks::from_netscape_html(&contents)?
+            } else {
+                bookmarks::from_json(&contents)?
+            };
+            let total = found.len();
+            let added = saved.import(found)?;
+            println!(
+                "Imported {} stories from {}, {} were saved already",
+                added,
+                path,
+                total - added
+            );
+        }
+        _ => return Err(USAGE.into()),
+    }
+    Ok(())
+}
+
+/// `HN_RS_ROW_FORMAT` lays out the rows of the story list, see [`RowFormat`].
+fn build_row_format() -> Result<RowFormat, Box<dyn std::error::Error>> {
+    match std::env::var("HN_RS_ROW_FORMAT") {
+        Ok(template) => {
+            Ok(RowFormat::parse(&template)
+                .map_err(|e| format!("Invalid HN_RS_ROW_FORMAT: {}", e))?)
+        }
+        Err(_) => Ok(RowFormat::default()),
+    }
+}
+
+/// `HN_RS_HISTORY_SIZE` caps how many read stories are remembered, and
+/// `HN_RS_HISTORY_DAYS` forgets those read longer ago than that.
+fn build_history_limits() -> Result<HistoryLimits, Box<dyn std::error::Error>> {
+    let mut limits = HistoryLimits::default();
+    if let Ok(size) = std::env::var("HN_RS_HISTORY_SIZE") {
+        limits.max_entries = size
+            .parse()
+            .map_err(|e| format!("Invalid HN_RS_HISTORY_SIZE: {}", e))?;
+    }
+    if let Ok(days) = std::env::var("HN_RS_HISTORY_DAYS") {
+        // Unsigned, and small enough that `Duration::days` can't overflow.
+        let days: u32 = days
+            .parse()
+            .map_err(|e| format!("Invalid HN_RS_HISTORY_DAYS: {}", e))?;
+        limits.max_age = Some(chrono::Duration::days(days.into()));
+    }
+    Ok(limits)
+}
+
+async fn run(
+    terminal: &mut DefaultTerminal,
+    client: HackerNewsClient,
+    search: SearchClient,
+    row_format: RowFormat,
+    history_limits: HistoryLimits,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (tx_action, rx_action) = unbounded_channel();
+    let (tx_data, mut rx_data) = unbounded_channel();
+    let (tx_error, mut rx_error) = unbounded_channel();
+
+    let mut app = App::new(tx_action.clone(), tx_error.clone());
+    app.set_row_format(row_format);
+    app.set_history_limits(history_limits);
+    let worker = tokio::spawn(Worker::new(client, search, tx_data, tx_error).run(rx_action));
 
     // Initial load
     tx_action.send(AppAction::Story(StoryType::Show))?;
 
-    loop {
-        if event::poll(Duration::from_millis(16))? {
-            let ev = event::read()?;
-            app.handle_event(ev);
-        }
-
-        if !app.is_running {
-            break;
-        }
+    let mut events = EventStream::new();
+    let mut spinner = tokio::time::interval(SPINNER_INTERVAL);
+    spinner.set_missed_tick_behavior(MissedTickBehavior::Skip);
 
+    // Sleeps until there is input, data, an error or, while something is
+    // loading or a message is up, a frame to show.
+    while app.is_running {
         if app.should_draw() {
-            terminal.draw(|f| {
-                app.draw(f).unwrap();
-            })?;
+            let mut res = Ok(());
+            terminal.draw(|f| res = app.draw(f))?;
+            res?;
         }
 
-        while let Ok(data_event) = rx_data.try_recv() {
-            app.update_data(data_event);
+        let status_deadline = app.status_deadline().map(Instant::from_std);
+        tokio::select! {
+            event = events.next() => match event {
+                Some(event) => app.handle_event(event?),
+                None => break,
+            },
+            Some(data) = rx_data.recv() => {
+                app.update_data(data);
+                while let Ok(data) = rx_data.try_recv() {
+                    app.update_data(data);
+                }
+            }
+            Some(error) = rx_error.recv() => app.show_error(error),
+            _ = spinner.tick(), if app.is_loading() => {}
+            _ = sleep_until(status_deadline.unwrap_or_else(Instant::now)),
+                if status_deadline.is_some() => app.expire_status(),
         }
     }
 
+    worker.abort();
+    app.save_seen_comments();
     Ok(())
 }
@@ -1,8 +1,9 @@
 use std::io::Result;
 
-use chrono::DateTime;
-use crossbeam_channel::Sender;
-use hackernews::get_items::ItemResponse;
+use hackernews::{
+    get_items::{ItemResponse, ItemType},
+    get_poll::Poll,
+};
 use html2text::config;
 use ratatui::{
     Frame,
@@ -13,24 +14,30 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, BorderType, Paragraph, Wrap},
 };
+use tokio::sync::mpsc::UnboundedSender;
 
 use crate::{
-    AppAction, AppData,
+    AppAction, AppData, AppError,
     app::Environment,
     components::{Component, DrawableComponent},
     panels::Comment,
+    storages::Visit,
 };
 
 pub struct Article {
     pub data: Option<ItemResponse>,
     pub focus: bool,
+    /// Whether the story is saved for later, shown in the title.
+    pub saved: bool,
+    poll: Option<Poll>,
     content_height: u16,
     scroll_offset: u16,
     scroll_offset_backup: u16,
     block_height: u16,
     block_width: u16,
     comment: Comment,
-    tx_action: Sender<AppAction>,
+    tx_action: UnboundedSender<AppAction>,
+    tx_error: UnboundedSender<AppError>,
 }
 
 impl DrawableComponent for Article {
@@ -59,12 +66,18 @@ impl DrawableComponent for Article {
                     Style::new()
                 }
             })
-            .title("Article")
+            .title(if self.saved { "Article ★" } else { "Article" })
             .title_bottom(Line::from({
                 if self.focus {
                     vec![
                         Span::styled("C", Style::default().fg(Color::Red)),
-                        Span::raw("omments"),
+                        Span::raw("omments "),
+                        Span::styled("U", Style::default().fg(Color::Red)),
+                        Span::raw("ser "),
+                        Span::styled("R", Style::default().fg(Color::Red)),
+                        Span::raw("efresh "),
+                        Span::styled("B", Style::default().fg(Color::Red)),
+                        Span::raw("ookmark"),
                     ]
                 } else {
                     vec![]
@@ -93,6 +106,11 @@ impl DrawableComponent for Article {
 
 impl Component for Article {
     fn event(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Char('r') {
+            self.refresh();
+            return;
+        }
+
         let action = &self.tx_action;
         if self.focus {
             if key.code == KeyCode::Char('j') {
@@ -101,10 +119,11 @@ impl Component for Article {
                 self.scroll(true);
             } else if key.code == KeyCode::Char('o') {
                 if let Some(item) = &self.data {
-                    if let Some(url) = &item.url {
-                        if let Err(e) = open::that(url) {
-                            eprintln!("Failed to open URL: {}", e);
-                        }
+                    let url = item.url.clone().unwrap_or_else(|| item.hn_permalink());
+                    if let Err(e) = open::that(&url) {
+                        let _ = self
+                            .tx_error
+                            .send(AppError::new(format!("Could not open {}: {}", url, e)));
                     }
                 }
             } else if key.code == KeyCode::Char('c') {
@@ -112,12 +131,8 @@ impl Component for Article {
                 self.focus = false;
                 self.scroll_offset_backup = self.scroll_offset;
                 self.scroll_offset = 0;
-                if self.data.is_some() {
-                    action
-                        .send(AppAction::Items(
-                            self.data.clone().unwrap().kids.unwrap_or_default(),
-                        ))
-                        .unwrap();
+                if let Some(item) = &self.data {
+                    let _ = action.send(AppAction::Items(item.kids.clone().unwrap_or_default()));
                 }
             }
         } else {
@@ -126,7 +141,7 @@ impl Component for Article {
                 self.focus = true;
                 self.scroll_offset = self.scroll_offset_backup;
                 self.scroll_offset_backup = 0;
-                action.send(AppAction::Items(Vec::new())).unwrap();
+                let _ = action.send(AppAction::Items(Vec::new()));
             }
             self.comment.event(key);
         }
@@ -138,6 +153,8 @@ impl Article {
         Self {
             data: None,
             focus: false,
+            saved: false,
+            poll: None,
             content_height: 0,
             scroll_offset: 0,
             scroll_offset_backup: 0,
@@ -145,24 +162,108 @@ impl Article {
             block_width: 0,
             comment: Comment::new(Vec::new()),
             tx_action: env.tx_action.clone(),
+            tx_error: env.tx_error.clone(),
         }
     }
 
     pub fn update_data(&mut self, data: AppData) {
-        self.comment.update_data(data);
+        match data {
+            AppData::Poll(poll) => {
+                if self
+                    .data
+                    .as_ref()
+                    .is_some_and(|item| item.id == poll.item.id)
+                {
+                    self.poll = Some(*poll);
+                }
+            }
+            AppData::Updates { items, .. } => {
+                if self.comment.focus
+                    && self.comment.contains_any(&items)
+                    && let Some(item) = &self.data
+                {
+                    let kids = item.kids.clone().unwrap_or_default();
+                    let _ = self
+                        .tx_action
+                        .send(AppAction::Refresh(Box::new(AppAction::Items(kids))));
+                }
+            }
+            data => self.comment.update_data(data),
+        }
     }
 
-
     pub fn set_data(&mut self, data: Option<ItemResponse>) {
         if self.data == data {
             return;
         }
+        // A fresh copy of the open story: keep the reader's place.
+        if let (Some(old), Some(new)) = (&self.data, &data)
+            && old.id == new.id
+        {
+            let kids = new.kids.clone().unwrap_or_default();
+            if self.comment.focus && old.kids != new.kids {
+                let _ = self
+                    .tx_action
+                    .send(AppAction::Refresh(Box::new(AppAction::Items(kids.clone()))));
+            }
+            if new.kind == ItemType::Poll {
+                let _ = self.tx_action.send(AppAction::Poll(new.id));
+            }
+            self.comment.set_ids(kids);
+            self.data = data;
+            return;
+        }
         self.data = data.clone();
         self.comment = Comment::new(
             data.as_ref()
                 .map_or(Vec::new(), |item| item.kids.clone().unwrap_or_default()),
         );
         self.scroll_offset = 0;
+        self.poll = None;
+        if let Some(item) = data.filter(|item| item.kind == ItemType::Poll) {
+            let _ = self.tx_action.send(AppAction::Poll(item.id));
+        }
+    }
+
+    /// Reloads the story and, while they are shown, its comments, bypassing
+    /// the cache.
+    fn refresh(&self) {
+        let Some(item) = &self.data else {
+            return;
+        };
+        let _ = self
+            .tx_action
+            .send(AppAction::Reload(Box::new(AppAction::Item(item.id))));
+        if self.comment.focus {
+            let kids = item.kids.clone().unwrap_or_default();
+            let _ = self
+                .tx_action
+                .send(AppAction::Reload(Box::new(AppAction::Items(kids))));
+        }
+    }
+
+    /// Highlights the comments that are new since `visit`.
+    pub fn set_last_visit(&mut self, visit: Option<Visit>) {
+        self.comment.set_seen(visit.map(|visit| visit.max_seen));
+    }
+
+    /// Highest id among the loaded comments.
+    pub fn max_comment_id(&self) -> Option<usize> {
+        self.comment.max_id()
+    }
+
+    pub fn comments_focused(&self) -> bool {
+        self.comment.focus
+    }
+
+    /// Whose profile `u` opens: the author of the story, or of the selected
+    /// comment.
+    pub fn author(&self) -> Option<String> {
+        if self.comment.focus {
+            self.comment.selected_author()
+        } else {
+            self.data.as_ref().and_then(|item| item.by.clone())
+        }
     }
 
     pub fn scroll(&mut self, up: bool) {
@@ -181,26 +282,104 @@ impl Article {
         self.data
             .as_ref()
             .map_or("No article selected".to_string(), |item| {
-                format!(
-                    "Title: {}\nAuthor: {}\nTime: {}\nURL: {}\n\n{}",
-                    item.title.clone().unwrap_or("No title".to_string()),
-                    item.by.as_deref().unwrap_or("Unknown"),
-                    DateTime::from_timestamp(item.time as i64, 0)
-                        .unwrap()
-                        .format("%Y-%m-%d %H:%M:%S"),
-                    item.url.as_deref().unwrap_or("No URL"),
-                    config::plain()
+                // Ask HN and other text posts carry their body in `text` and
+                // usually have no URL, so link to the discussion instead.
+                let link = match &item.url {
+                    Some(url) => format!("URL: {}", url),
+                    None => format!("Discussion: {}", item.hn_permalink()),
+                };
+                let body = match item.text.as_deref() {
+                    Some(text) => config::plain()
                         .link_footnotes(false)
                         .no_link_wrapping()
                         .string_from_read(
-                            item.text
-                                .as_deref()
-                                .unwrap_or("No content available")
-                                .as_bytes(),
-                            (self.block_width - 2).into()
+                            text.as_bytes(),
+                            self.block_width.saturating_sub(2).max(1).into(),
                         )
-                        .unwrap()
+                        .unwrap_or_default(),
+                    None if item.url.is_some() => "Press o to open the article".to_string(),
+                    None => "No content available".to_string(),
+                };
+                let poll = match (&self.poll, item.kind == ItemType::Poll) {
+                    (Some(poll), _) => {
+                        format!("\n\n{}", poll_chart(poll, self.block_width.into()))
+                    }
+                    (None, true) => "\n\nLoading poll...".to_string(),
+                    (None, false) => String::new(),
+                };
+                format!(
+                    "Title: {}\nAuthor: {}\nTime: {}\nPoints: {} | Comments: {}\n{}{}\n\n{}",
+                    item.title.clone().unwrap_or("No title".to_string()),
+                    item.by.as_deref().unwrap_or("Unknown"),
+                    match item.time {
+                        0 => "Unknown".to_string(),
+                        _ => item.created_at().format("%Y-%m-%d %H:%M:%S").to_string(),
+                    },
+                    item.score.unwrap_or(0),
+                    item.descendants.unwrap_or(0),
+                    link,
+                    poll,
+                    body.trim_end()
                 )
             })
     }
 }
+
+/// Renders the poll options, most voted first, as one labelled bar each.
+fn poll_chart(poll: &Poll, width: usize) -> String {
+    let total = poll.total_votes();
+    let bar_width = width.saturating_sub(20).clamp(10, 40);
+
+    let mut lines = vec![format!("Poll: {} votes", total)];
+    for option in poll.ranked() {
+        let ratio = if total == 0 {
+            0.0
+        } else {
+            option.score as f64 / total as f64
+        };
+        let filled = (ratio * bar_width as f64).round() as usize;
+        lines.push(format!(
+            "{}\n{}{} {:>3.0}% ({})",
+            html2text::from_read(option.text.as_bytes(), width.max(1))
+                .unwrap_or_default()
+                .trim_end(),
+            "█".repeat(filled),
+            "░".repeat(bar_width - filled),
+            ratio * 100.0,
+            option.score
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use hackernews::get_poll::PollOption;
+
+    use super::*;
+
+    #[test]
+    fn test_poll_chart() {
+        let item: ItemResponse =
+            serde_json::from_str(r#"{"id": 1, "type": "poll", "time": 0}"#).unwrap();
+        let option = |id, text: &str, score| PollOption {
+            id,
+            text: text.to_string(),
+            score,
+        };
+        let poll = Poll {
+            item,
+            options: vec![option(2, "vim", 1), option(3, "emacs", 3)],
+        };
+
+        let chart = poll_chart(&poll, 30);
+        let lines = chart.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "Poll: 4 votes");
+        assert_eq!(lines[1], "emacs");
+        assert_eq!(
+            lines[2],
+            format!("{}{}  75% (3)", "█".repeat(8), "░".repeat(2))
+        );
+        assert_eq!(lines[3], "vim");
+    }
+}
@@ -1,27 +1,53 @@
-use std::{io::Result, usize};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Result,
+};
 
+use chrono::Utc;
 use hackernews::get_items::ItemResponse;
 use html2text::config;
 use ratatui::{
     Frame,
     crossterm::event::{KeyCode, KeyEvent},
     layout::Rect,
-    style::Stylize,
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
     widgets::Paragraph,
 };
 
 use crate::{
     AppData,
     components::{Component, DrawableComponent},
+    panels::row_format::relative_age,
 };
 
+/// Columns each level of replies is indented by.
+const INDENT: usize = 2;
+
+/// A comment as laid out in the tree, in display order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Node {
+    id: usize,
+    depth: usize,
+    parent: Option<usize>,
+}
+
 #[derive(Debug)]
 pub struct Comment {
     ids: Vec<usize>,
     data: Option<Vec<ItemResponse>>,
+    /// The comment under the cursor.
+    selected: Option<usize>,
+    /// Comments whose replies are hidden.
+    collapsed: HashSet<usize>,
+    /// Highest comment id loaded at the last visit. Higher ones are new.
+    seen: Option<usize>,
     scroll_offset: u16,
-    content_height: u16,
-    block_width: u16,
+    height: u16,
+    /// Wrapped text of each comment at `rendered_width`, so that drawing does
+    /// not run the whole thread through html2text every time.
+    rendered: HashMap<usize, Vec<String>>,
+    rendered_width: u16,
     pub focus: bool,
 }
 
@@ -29,9 +55,13 @@ impl Comment {
     pub fn new(ids: Vec<usize>) -> Self {
         Comment {
             ids,
+            selected: None,
+            collapsed: HashSet::new(),
+            seen: None,
             scroll_offset: 0,
-            content_height: 0,
-            block_width: 0,
+            height: 0,
+            rendered: HashMap::new(),
+            rendered_width: 0,
             focus: false,
             data: None,
         }
@@ -39,72 +69,306 @@ impl Comment {
 
     pub fn update_data(&mut self, data: AppData) {
         if let AppData::Comment(Some(data)) = data {
-            if let Some(mut items) = self.data.clone() {
-                if items.iter().map(|item| item.id).all(|id| id != data.id) {
-                    items.push(data);
-                    self.data = Some(items);
+            walk(&data, 0, None, &mut |item, _| {
+                self.rendered.remove(&item.id);
+                true
+            });
+            if let Some(items) = &mut self.data {
+                match items.iter_mut().find(|item| item.id == data.id) {
+                    Some(item) => *item = *data,
+                    None => items.push(*data),
                 }
             } else {
-                self.data = Some(vec![data]);
+                self.data = Some(vec![*data]);
             }
         }
     }
 
-    fn formater(&self, item: &ItemResponse, max_width: usize, indent: Option<i16>) -> String {
-        let indent_space = " ".repeat(indent.unwrap_or(0) as usize);
-        let mut text = format!(
-            "{}({}): {}",
-            if indent_space.len() > 0 {
-                format!("{}└─&gt;", indent_space)
-            } else {
-                indent_space
-            },
-            item.by.clone().unwrap_or("".to_string()),
-            html_escape::decode_html_entities(&item.text.clone().unwrap_or("".to_string()))
-                .to_string()
-        );
-        if let Some(children) = &item.children {
-            let children_text = children
-                .into_iter()
-                .map(|child| {
-                    let text = self.formater(child, max_width, Some(indent.unwrap_or(0) + 1));
-                    return text;
-                })
-                .collect::<Vec<_>>()
-                .join("\n");
-            text = text + "\n" + &children_text;
-        }
-
-        return if indent.is_none() {
-            let show_content = config::rich()
-                .link_footnotes(false)
-                .string_from_read(format!("<pre>{}</pre>", text).as_bytes(), max_width)
-                .unwrap();
-
-            show_content
+    /// Replaces the top-level comment ids, dropping loaded comments that are
+    /// no longer among them.
+    pub fn set_ids(&mut self, ids: Vec<usize>) {
+        if let Some(items) = &mut self.data {
+            items.retain(|item| ids.contains(&item.id));
+        }
+        self.ids = ids;
+    }
+
+    /// Whether any of `ids` is a loaded comment, at any depth.
+    pub fn contains_any(&self, ids: &[usize]) -> bool {
+        let mut found = false;
+        for thread in self.threads() {
+            walk(thread, 0, None, &mut |item, _| {
+                found |= ids.contains(&item.id);
+                !found
+            });
+        }
+        found
+    }
+
+    /// Highlights the comments above `max_seen`, or none if the thread was
+    /// never visited.
+    pub fn set_seen(&mut self, max_seen: Option<usize>) {
+        self.seen = max_seen;
+    }
+
+    fn is_new(&self, id: usize) -> bool {
+        self.seen.is_some_and(|max_seen| id > max_seen)
+    }
+
+    /// Highest loaded comment id.
+    pub fn max_id(&self) -> Option<usize> {
+        let mut max = None;
+        for thread in self.threads() {
+            walk(thread, 0, None, &mut |item, _| {
+                max = max.max(Some(item.id));
+                true
+            });
+        }
+        max
+    }
+
+    /// Number of loaded comments that are new since the last visit.
+    fn new_count(&self) -> usize {
+        let mut count = 0;
+        for thread in self.threads() {
+            walk(thread, 0, None, &mut |item, _| {
+                count += self.is_new(item.id) as usize;
+                true
+            });
+        }
+        count
+    }
+
+    /// Moves to the next or previous new comment, expanding the thread it
+    /// is in.
+    fn select_new(&mut self, up: bool) {
+        let mut all = Vec::new();
+        for thread in self.threads() {
+            walk(thread, 0, None, &mut |_, node| {
+                all.push(node);
+                true
+            });
+        }
+        let current = self
+            .selected
+            .and_then(|id| all.iter().position(|node| node.id == id));
+        let found = match (current, up) {
+            (Some(current), false) => all[current + 1..].iter().find(|node| self.is_new(node.id)),
+            (Some(current), true) => all[..current]
+                .iter()
+                .rev()
+                .find(|node| self.is_new(node.id)),
+            (None, _) => all.iter().find(|node| self.is_new(node.id)),
+        };
+        let Some(&found) = found else {
+            return;
+        };
+
+        // Show it, even if it is the reply of a collapsed comment.
+        let mut parent = found.parent;
+        while let Some(id) = parent {
+            self.collapsed.remove(&id);
+            parent = all
+                .iter()
+                .find(|node| node.id == id)
+                .and_then(|node| node.parent);
+        }
+        self.selected = Some(found.id);
+    }
+
+    /// Author of the comment under the cursor.
+    pub fn selected_author(&self) -> Option<String> {
+        let selected = self.selected?;
+        let mut author = None;
+        for thread in self.threads() {
+            walk(thread, 0, None, &mut |item, _| {
+                if item.id == selected {
+                    author = item.by.clone();
+                }
+                true
+            });
+        }
+        author
+    }
+
+    /// Loaded top-level comments, in the order of `ids`.
+    fn threads(&self) -> Vec<&ItemResponse> {
+        let Some(items) = &self.data else {
+            return Vec::new();
+        };
+        self.ids
+            .iter()
+            .filter_map(|id| items.iter().find(|item| item.id == *id))
+            .collect()
+    }
+
+    /// The comments that are shown, replies of collapsed ones left out.
+    fn nodes(&self) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        for thread in self.threads() {
+            walk(thread, 0, None, &mut |item, node| {
+                nodes.push(node);
+                !self.collapsed.contains(&item.id)
+            });
+        }
+        nodes
+    }
+
+    /// Index of the selected comment among `nodes`, falling back to the
+    /// first one.
+    fn selected_index(&self, nodes: &[Node]) -> Option<usize> {
+        let position = self
+            .selected
+            .and_then(|id| nodes.iter().position(|node| node.id == id));
+        position.or(if nodes.is_empty() { None } else { Some(0) })
+    }
+
+    fn select(&mut self, nodes: &[Node], index: Option<usize>) {
+        if let Some(node) = index.and_then(|index| nodes.get(index)) {
+            self.selected = Some(node.id);
+        }
+    }
+
+    /// Moves the cursor by one comment.
+    fn select_next(&mut self, up: bool) {
+        let nodes = self.nodes();
+        let Some(current) = self.selected_index(&nodes) else {
+            return;
+        };
+        let index = if up {
+            current.checked_sub(1)
         } else {
-            text
+            Some(current + 1)
         };
+        self.select(&nodes, index.or(Some(current)));
     }
 
-    pub fn scroll(&mut self, up: bool) {
-        self.scroll_offset = {
-            if up {
-                self.scroll_offset.saturating_sub(1)
-            } else {
-                self.scroll_offset
-                    .saturating_add(1)
-                    .min(self.content_height)
-            }
+    fn select_parent(&mut self) {
+        let nodes = self.nodes();
+        let Some(current) = self.selected_index(&nodes) else {
+            return;
         };
+        if let Some(parent) = nodes[current].parent {
+            self.selected = Some(parent);
+        }
+    }
+
+    /// Moves to the next or previous reply to the same comment.
+    fn select_sibling(&mut self, up: bool) {
+        let nodes = self.nodes();
+        let Some(current) = self.selected_index(&nodes) else {
+            return;
+        };
+        let depth = nodes[current].depth;
+        let is_sibling_or_above = |node: &&Node| node.depth <= depth;
+        let found = if up {
+            nodes[..current].iter().rev().find(is_sibling_or_above)
+        } else {
+            nodes[current + 1..].iter().find(is_sibling_or_above)
+        };
+        // Anything less deep is a parent's sibling: there is no more sibling.
+        if let Some(node) = found.filter(|node| node.depth == depth) {
+            self.selected = Some(node.id);
+        }
+    }
+
+    /// Moves to the next top-level comment, or back to the start of the
+    /// current or previous one.
+    fn select_thread(&mut self, up: bool) {
+        let nodes = self.nodes();
+        let Some(current) = self.selected_index(&nodes) else {
+            return;
+        };
+        let found = if up {
+            nodes[..current].iter().rev().find(|node| node.depth == 0)
+        } else {
+            nodes[current + 1..].iter().find(|node| node.depth == 0)
+        };
+        if let Some(node) = found {
+            self.selected = Some(node.id);
+        }
+    }
+
+    fn toggle_collapsed(&mut self) {
+        let nodes = self.nodes();
+        let Some(current) = self.selected_index(&nodes) else {
+            return;
+        };
+        let id = nodes[current].id;
+        self.selected = Some(id);
+        if !self.collapsed.remove(&id) {
+            self.collapsed.insert(id);
+        }
+    }
+
+    /// Keeps the selected comment in view, given its first line and its
+    /// number of lines.
+    fn scroll_to_selected(&mut self, start: u16, len: u16) {
+        let len = len.min(self.height);
+        if start < self.scroll_offset {
+            self.scroll_offset = start;
+        } else if start + len > self.scroll_offset + self.height {
+            self.scroll_offset = start + len - self.height;
+        }
+    }
+}
+
+/// Visits `item` and its loaded replies depth first. `visit` returns whether
+/// to go into the replies of the item it was given.
+fn walk<'a>(
+    item: &'a ItemResponse,
+    depth: usize,
+    parent: Option<usize>,
+    visit: &mut impl FnMut(&'a ItemResponse, Node) -> bool,
+) {
+    let node = Node {
+        id: item.id,
+        depth,
+        parent,
+    };
+    if visit(item, node) {
+        for child in item.children.iter().flatten() {
+            walk(child, depth + 1, Some(item.id), visit);
+        }
+    }
+}
+
+/// Number of replies under `item`, at any depth.
+fn reply_count(item: &ItemResponse) -> usize {
+    match &item.children {
+        Some(children) => children.iter().map(|child| 1 + reply_count(child)).sum(),
+        None => item.kids.as_ref().map_or(0, Vec::len),
     }
 }
 
+/// The text of a comment, wrapped to `width` columns.
+fn render_text(item: &ItemResponse, width: usize) -> Vec<String> {
+    if item.is_deleted() {
+        return vec!["[deleted]".to_string()];
+    }
+    let text = config::plain()
+        .link_footnotes(false)
+        .no_link_wrapping()
+        .string_from_read(item.text.as_deref().unwrap_or("").as_bytes(), width.max(1))
+        .unwrap_or_default();
+    let mut lines = text.lines().map(str::to_string).collect::<Vec<_>>();
+    if item.is_dead() {
+        lines.insert(0, "[flagged]".to_string());
+    }
+    lines
+}
+
 impl DrawableComponent for Comment {
     fn draw(&mut self, f: &mut Frame, rect: Rect) -> Result<()> {
-        let block = ratatui::widgets::Block::bordered()
+        let new_count = self.new_count();
+        let title = if new_count > 0 {
+            format!("Comments ({} new)", new_count)
+        } else {
+            "Comments".to_string()
+        };
+        let mut block = ratatui::widgets::Block::bordered()
             .border_type(ratatui::widgets::BorderType::Rounded)
-            .title("Comments")
+            .title(title)
             .border_style({
                 if self.focus {
                     ratatui::style::Style::new().blue()
@@ -112,28 +376,105 @@ impl DrawableComponent for Comment {
                     ratatui::style::Style::new()
                 }
             });
+        if self.focus {
+            let key = |key| Span::styled(key, Style::default().fg(Color::Red));
+            block = block.title_bottom(Line::from(vec![
+                key("Space"),
+                Span::raw(" fold "),
+                key("p"),
+                Span::raw("arent "),
+                key("n"),
+                Span::raw("/"),
+                key("N"),
+                Span::raw(" sibling "),
+                key("]"),
+                Span::raw("/"),
+                key("["),
+                Span::raw(" thread "),
+                key("Tab"),
+                Span::raw("/"),
+                key("S-Tab"),
+                Span::raw(" new"),
+            ]));
+        }
+
+        if self.ids.is_empty() {
+            f.render_widget(Paragraph::new("No comments available").block(block), rect);
+            return Ok(());
+        }
+        if self.data.is_none() {
+            f.render_widget(Paragraph::new("Loading comments...").block(block), rect);
+            return Ok(());
+        }
+
+        let width = rect.width.saturating_sub(2);
+        if width != self.rendered_width {
+            self.rendered.clear();
+            self.rendered_width = width;
+        }
+        self.height = rect.height.saturating_sub(2);
+
+        let nodes = self.nodes();
+        let selected = self.selected_index(&nodes).map(|index| nodes[index].id);
+        self.selected = selected;
+
+        let mut items = HashMap::new();
+        for thread in self.data.iter().flatten() {
+            walk(thread, 0, None, &mut |item, _| {
+                items.insert(item.id, item);
+                true
+            });
+        }
+
+        let now = Utc::now();
+        let mut lines = Vec::new();
+        let mut selected_lines = (0, 0);
+        for node in &nodes {
+            let Some(item) = items.get(&node.id) else {
+                continue;
+            };
+            let indent = " ".repeat(node.depth * INDENT);
+            let start = lines.len() as u16;
+
+            let mut header = vec![
+                Span::raw(indent.clone()),
+                Span::raw(item.by.clone().unwrap_or("[deleted]".to_string())).bold(),
+                Span::raw(format!(" {}", relative_age(item.created_at(), now))).dark_gray(),
+            ];
+            if self.is_new(node.id) {
+                header.push(Span::raw(" new").green().bold());
+            }
+            let replies = reply_count(item);
+            if self.collapsed.contains(&node.id) && replies > 0 {
+                header.push(Span::raw(format!(" [+{}]", replies)).yellow());
+            }
+            let mut header = Line::from(header);
+            if Some(node.id) == selected {
+                header = header.style(Style::default().bg(Color::Blue));
+            }
+            lines.push(header);
+
+            let text_width = (width as usize).saturating_sub(node.depth * INDENT);
+            let text = self
+                .rendered
+                .entry(node.id)
+                .or_insert_with(|| render_text(item, text_width));
+            lines.extend(
+                text.iter()
+                    .map(|line| Line::from(format!("{}{}", indent, line))),
+            );
+            lines.push(Line::default());
+
+            if Some(node.id) == selected {
+                selected_lines = (start, lines.len() as u16 - start);
+            }
+        }
+        self.scroll_to_selected(selected_lines.0, selected_lines.1);
+
         f.render_widget(
-            if self.ids.is_empty() {
-                Paragraph::new("No comments available").block(block)
-            } else if self.data.is_none() {
-                Paragraph::new("Loading comments...").block(block)
-            } else {
-                let content = self.data.as_ref().map_or("".to_string(), |data| {
-                    data.iter()
-                        .map(|item| {
-                            self.formater(item, rect.width.saturating_sub(2) as usize, None)
-                        })
-                        .collect::<Vec<_>>()
-                        .join("")
-                });
-                self.block_width = rect.width.saturating_sub(2);
-                self.content_height = content.lines().fold(0, |acc, line| {
-                    acc + (line.len() as u16 / self.block_width).saturating_add(1)
-                });
-                Paragraph::new(content)
-                    .block(block)
-                    .scroll((self.scroll_offset, 0))
-            },
+            Paragraph::new(lines)
+                .block(block)
+                .scroll((self.scroll_offset, 0)),
             rect,
         );
 
@@ -143,12 +484,21 @@ impl DrawableComponent for Comment {
 
 impl Component for Comment {
     fn event(&mut self, key: KeyEvent) {
-        if self.focus {
-            if key.code == KeyCode::Char('j') {
-                self.scroll(false);
-            } else if key.code == KeyCode::Char('k') {
-                self.scroll(true);
-            }
+        if !self.focus {
+            return;
+        }
+        match key.code {
+            KeyCode::Char('j') => self.select_next(false),
+            KeyCode::Char('k') => self.select_next(true),
+            KeyCode::Char(' ') => self.toggle_collapsed(),
+            KeyCode::Char('p') => self.select_parent(),
+            KeyCode::Char('n') => self.select_sibling(false),
+            KeyCode::Char('N') => self.select_sibling(true),
+            KeyCode::Char(']') => self.select_thread(false),
+            KeyCode::Char('[') => self.select_thread(true),
+            KeyCode::Tab => self.select_new(false),
+            KeyCode::BackTab => self.select_new(true),
+            _ => {}
         }
     }
 }
@@ -156,26 +506,145 @@ impl Component for Comment {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ratatui::{Terminal, backend::TestBackend, crossterm::event::KeyModifiers};
+
+    fn comment(id: usize, children: Vec<ItemResponse>) -> ItemResponse {
+        ItemResponse {
+            id,
+            by: Some(format!("user{}", id)),
+            kids: Some(children.iter().map(|child| child.id).collect()),
+            children: Some(children),
+            ..ItemResponse::default()
+        }
+    }
+
+    /// 1 ─┬─ 2 ── 3
+    ///    └─ 4
+    /// 5
+    fn thread() -> Comment {
+        let mut comments = Comment::new(vec![1, 5]);
+        comments.focus = true;
+        let first = comment(
+            1,
+            vec![comment(2, vec![comment(3, vec![])]), comment(4, vec![])],
+        );
+        comments.update_data(AppData::Comment(Some(Box::new(comment(5, vec![])))));
+        comments.update_data(AppData::Comment(Some(Box::new(first))));
+        comments
+    }
+
+    fn press(comments: &mut Comment, c: char) {
+        comments.event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut comments = thread();
+        // Threads follow `ids`, whatever order they arrived in.
+        let ids = comments
+            .nodes()
+            .iter()
+            .map(|node| node.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+
+        press(&mut comments, 'j');
+        press(&mut comments, 'j');
+        assert_eq!(comments.selected, Some(3));
+        assert_eq!(comments.selected_author().as_deref(), Some("user3"));
+        press(&mut comments, 'p');
+        assert_eq!(comments.selected, Some(2));
+        press(&mut comments, 'n');
+        assert_eq!(comments.selected, Some(4));
+        press(&mut comments, 'n');
+        assert_eq!(comments.selected, Some(4));
+        press(&mut comments, 'N');
+        assert_eq!(comments.selected, Some(2));
+        press(&mut comments, ']');
+        assert_eq!(comments.selected, Some(5));
+        press(&mut comments, '[');
+        assert_eq!(comments.selected, Some(1));
+        press(&mut comments, 'k');
+        assert_eq!(comments.selected, Some(1));
+    }
 
     #[test]
-    fn test_formater() {
-        let comment = Comment::new(vec![]);
-        let mut item = ItemResponse::default();
-        let mut item_inside = item.clone();
-        item_inside.children = Some(vec![ItemResponse::default()]);
-        item.children = Some(vec![
-            ItemResponse::default(),
-            ItemResponse::default(),
-            item_inside,
-        ]);
-
-        let str = comment.formater(&item, 80, None);
-        let res = "(Linux): This is a default item\n └─>\
-                    (Linux): This is a default item\n └─>\
-                    (Linux): This is a default item\n └─>\
-                    (Linux): This is a default item\n  └─>\
-                    (Linux): This is a default item\n";
-
-        assert_eq!(str, res);
+    fn test_collapse() {
+        let mut comments = thread();
+        press(&mut comments, ' ');
+        assert_eq!(comments.selected, Some(1));
+        let ids = comments
+            .nodes()
+            .iter()
+            .map(|node| node.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, 5]);
+        assert_eq!(reply_count(comments.threads()[0]), 3);
+
+        press(&mut comments, 'j');
+        assert_eq!(comments.selected, Some(5));
+        press(&mut comments, 'k');
+        press(&mut comments, ' ');
+        assert_eq!(comments.nodes().len(), 5);
+    }
+
+    #[test]
+    fn test_jump_to_new_comments() {
+        let mut comments = thread();
+        assert_eq!(comments.max_id(), Some(5));
+        comments.set_seen(Some(2));
+        assert_eq!(comments.new_count(), 3);
+
+        // The new reply 3 is hidden under 1.
+        press(&mut comments, ' ');
+        comments.event(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+        assert_eq!(comments.selected, Some(3));
+        assert_eq!(comments.nodes().len(), 5);
+        comments.event(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+        assert_eq!(comments.selected, Some(4));
+        comments.event(KeyEvent::new(KeyCode::BackTab, KeyModifiers::NONE));
+        assert_eq!(comments.selected, Some(3));
+
+        // A first visit has nothing new.
+        comments.set_seen(None);
+        assert_eq!(comments.new_count(), 0);
+    }
+
+    #[test]
+    fn test_draw_collapsed_count() {
+        let mut comments = thread();
+        press(&mut comments, ' ');
+        let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+        terminal
+            .draw(|f| comments.draw(f, f.area()).unwrap())
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let row = (0..buffer.area.width)
+            .map(|x| buffer[(x, 1)].symbol())
+            .collect::<String>();
+        assert!(row.contains("user1"), "{}", row);
+        assert!(row.contains("[+3]"), "{}", row);
+    }
+
+    #[test]
+    fn test_update_replaces_refreshed_comment() {
+        let mut comment = Comment::new(vec![1]);
+        let mut item = ItemResponse {
+            id: 1,
+            children: Some(vec![ItemResponse {
+                id: 2,
+                ..ItemResponse::default()
+            }]),
+            ..ItemResponse::default()
+        };
+        comment.update_data(AppData::Comment(Some(Box::new(item.clone()))));
+        assert!(comment.contains_any(&[2]));
+
+        item.children = None;
+        comment.update_data(AppData::Comment(Some(Box::new(item))));
+        assert_eq!(comment.data.as_ref().map(Vec::len), Some(1));
+        assert!(comment.contains_any(&[1]));
+        assert!(!comment.contains_any(&[2]));
     }
 }
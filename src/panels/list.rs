@@ -1,6 +1,13 @@
-use std::io::Result;
+use std::{
+    collections::{HashMap, HashSet},
+    io::Result,
+};
 
-use hackernews::{StoryType, get_items::ItemResponse};
+use chrono::{DateTime, Local, Utc};
+use hackernews::{
+    StoryType,
+    get_items::{ItemResponse, ItemType},
+};
 use ratatui::{
     Frame,
     crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
@@ -9,15 +16,33 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, BorderType, List, ListItem, block::Title},
 };
+use tokio::sync::mpsc::UnboundedSender;
 
 use crate::{
+    AppAction, AppError,
     app::Environment,
     components::{Component, DrawableComponent, Loading},
-    storages::ReadHistory,
+    panels::RowFormat,
+    storages::{Bookmark, Bookmarks, HistoryLimits, ReadHistory, Visit, Visits, bookmarks},
 };
 
+/// Number of comment threads whose last visit is remembered.
+const MAX_VISITS: usize = 500;
+/// Number of stories requested at once.
+const PAGE_SIZE: usize = 30;
+/// How close to the last requested row the selection gets before the next
+/// page is requested.
+const PAGE_MARGIN: usize = 10;
+
 pub struct ListBlock {
-    pub data: Vec<ItemResponse>,
+    /// Story ids in the feed's rank order. Rows not fetched yet are shown as
+    /// placeholders.
+    ids: Vec<usize>,
+    items: HashMap<usize, ItemResponse>,
+    /// Loaded rows to fetch again the next time they are requested.
+    stale: HashSet<usize>,
+    /// How many ids, from the top, have been requested.
+    requested: usize,
     pub selected: u16,
     pub topic: StoryType,
     pub focus: bool,
@@ -25,19 +50,435 @@ pub struct ListBlock {
     loading: Loading,
     height: u16,
     readed_history: ReadHistory,
+    visits: Visits,
+    bookmarks: Bookmarks,
+    /// The query being typed after `/`, or what to filter a collection by.
+    search_input: Option<String>,
+    /// The search whose results are listed instead of the topic.
+    search: Option<Search>,
+    /// The stories kept here, listed instead of the topic. They come after
+    /// [`StoryType::New`] in the topic cycle.
+    collection: Option<Collection>,
+    /// The tags being typed for the selected saved story, after `t`.
+    tags_input: Option<String>,
+    /// Waiting for `y` to clear the read history, after `D`.
+    confirm_clear: bool,
+    row_format: RowFormat,
+    tx_action: UnboundedSender<AppAction>,
+    tx_error: UnboundedSender<AppError>,
+}
+
+enum Collection {
+    /// Stories saved for later, only those with `tag` if set.
+    Saved { tag: Option<String> },
+    /// Stories read before, newest first, only those whose title contains
+    /// `query` if set.
+    History { query: Option<String> },
+}
+
+struct Search {
+    query: String,
+    page: usize,
+    has_more: bool,
 }
 
 impl ListBlock {
-    pub fn new(_env: &Environment, focus: bool) -> Self {
+    pub fn new(env: &Environment, focus: bool) -> Self {
         Self {
-            data: vec![],
+            ids: Vec::new(),
+            items: HashMap::new(),
+            stale: HashSet::new(),
+            requested: 0,
             topic: StoryType::Show,
             focus,
             selected: 0,
             list_top_cursor: 0,
             height: 0,
             loading: Loading::new(),
-            readed_history: ReadHistory::new(100),
+            readed_history: ReadHistory::new(HistoryLimits::default()).unwrap_or_else(|e| {
+                let _ = env.tx_error.send(AppError::new(format!(
+                    "Read history will not be saved: {}",
+                    e
+                )));
+                ReadHistory::in_memory(HistoryLimits::default())
+            }),
+            visits: Visits::new(MAX_VISITS).unwrap_or_else(|e| {
+                let _ = env.tx_error.send(AppError::new(format!(
+                    "Comment visits will not be saved: {}",
+                    e
+                )));
+                Visits::in_memory(MAX_VISITS)
+            }),
+            bookmarks: Bookmarks::new().unwrap_or_else(|e| {
+                let _ = env.tx_error.send(AppError::new(format!(
+                    "Saved stories will not be kept: {}",
+                    e
+                )));
+                Bookmarks::in_memory()
+            }),
+            search_input: None,
+            search: None,
+            collection: None,
+            tags_input: None,
+            confirm_clear: false,
+            row_format: RowFormat::default(),
+            tx_action: env.tx_action.clone(),
+            tx_error: env.tx_error.clone(),
+        }
+    }
+
+    pub fn set_row_format(&mut self, row_format: RowFormat) {
+        self.row_format = row_format;
+    }
+
+    pub fn set_history_limits(&mut self, limits: HistoryLimits) -> Result<()> {
+        self.readed_history
+            .set_limits(limits, Utc::now().timestamp())
+    }
+
+    /// The story under the cursor, unless it is still loading.
+    pub fn selected_item(&self) -> Option<&ItemResponse> {
+        self.ids
+            .get(self.selected as usize)
+            .and_then(|id| self.items.get(id))
+    }
+
+    pub fn contains_any(&self, ids: &[usize]) -> bool {
+        self.ids.iter().any(|id| ids.contains(id))
+    }
+
+    /// Replaces the rows with a feed, keeping the selected story selected if
+    /// it is still there, and requests the rows needed to fill the view.
+    pub fn set_ids(&mut self, ids: Vec<usize>) {
+        let selected_id = self.ids.get(self.selected as usize).copied();
+        let requested = self.requested.max(PAGE_SIZE);
+        self.ids = ids;
+        self.items.retain(|id, _| self.ids.contains(id));
+        self.stale.retain(|id| self.items.contains_key(id));
+        self.requested = 0;
+        self.loading.set_loading(false);
+
+        match selected_id.and_then(|id| self.ids.iter().position(|&row| row == id)) {
+            Some(position) => {
+                self.selected = position as u16;
+                if self.selected < self.list_top_cursor {
+                    self.list_top_cursor = self.selected;
+                } else if self.height > 0 && self.selected >= self.list_top_cursor + self.height {
+                    self.list_top_cursor = self.selected + 1 - self.height;
+                }
+            }
+            None => {
+                self.selected = 0;
+                self.list_top_cursor = 0;
+            }
+        }
+
+        self.request_rows(requested);
+        self.load_more();
+    }
+
+    /// Fills in fetched rows. Items that are not part of the list are ignored.
+    pub fn add_items(&mut self, items: Vec<ItemResponse>) {
+        for item in items {
+            if self.ids.contains(&item.id) {
+                self.items.insert(item.id, item);
+            }
+        }
+    }
+
+    /// Marks the loaded rows among `ids` as changed, so that they are
+    /// fetched again when the list is next refreshed.
+    pub fn invalidate(&mut self, ids: &[usize]) {
+        let loaded = ids.iter().filter(|id| self.items.contains_key(id));
+        self.stale.extend(loaded);
+    }
+
+    /// Requests the rows above `end` that were not requested yet, leaving out
+    /// those already loaded unless they are stale.
+    fn request_rows(&mut self, end: usize) {
+        let end = end.min(self.ids.len());
+        if end <= self.requested {
+            return;
+        }
+
+        let ids = self.ids[self.requested..end]
+            .iter()
+            .copied()
+            .filter(|id| !self.items.contains_key(id) || self.stale.remove(id))
+            .collect::<Vec<_>>();
+        self.requested = end;
+        if !ids.is_empty() {
+            let _ = self.tx_action.send(AppAction::StoryItems(ids));
+        }
+    }
+
+    /// How far `C-f` and `C-b` move: a screen less one row, and at least one
+    /// row even when not a single story fits.
+    fn page_step(&self) -> u16 {
+        self.height.saturating_sub(1).max(1)
+    }
+
+    /// Requests more rows once the selection or the view gets close to the
+    /// last requested one.
+    fn load_more(&mut self) {
+        if self.search.is_some() {
+            self.load_more_results();
+            return;
+        }
+
+        let needed = (self.selected as usize + PAGE_MARGIN)
+            .max(self.list_top_cursor as usize + self.height as usize);
+        if needed >= self.requested {
+            self.request_rows(needed.max(self.requested + PAGE_SIZE));
+        }
+    }
+
+    /// Whether keys are being typed into a prompt, or a question waits for
+    /// an answer.
+    pub fn is_editing(&self) -> bool {
+        self.search_input.is_some() || self.tags_input.is_some() || self.confirm_clear
+    }
+
+    /// Whether the saved or read stories are listed instead of a topic.
+    pub fn is_showing_collection(&self) -> bool {
+        self.collection.is_some()
+    }
+
+    fn show_collection(&mut self, collection: Collection) {
+        self.collection = Some(collection);
+        self.list_collection();
+    }
+
+    /// Lists the stories of the collection that match its filter, and
+    /// requests fresh copies of them. The selected story stays selected if
+    /// still listed.
+    fn list_collection(&mut self) {
+        match &self.collection {
+            Some(Collection::Saved { tag }) => {
+                let saved = self
+                    .bookmarks
+                    .list(tag.as_deref())
+                    .into_iter()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let ids = saved.iter().map(|bookmark| bookmark.id).collect();
+                // Shown until the fresh copies arrive.
+                for bookmark in saved {
+                    if !self.items.contains_key(&bookmark.id) {
+                        self.stale.insert(bookmark.id);
+                        self.items.insert(bookmark.id, bookmark_item(bookmark));
+                    }
+                }
+                self.set_ids(ids);
+            }
+            Some(Collection::History { query }) => {
+                let query = query.as_deref().map(str::to_lowercase);
+                let ids = self
+                    .readed_history
+                    .reads()
+                    .filter(|read| {
+                        let Some(query) = &query else {
+                            return true;
+                        };
+                        let title = read.title.as_ref().or_else(|| {
+                            self.items
+                                .get(&read.id)
+                                .and_then(|item| item.title.as_ref())
+                        });
+                        title.is_some_and(|title| title.to_lowercase().contains(query))
+                    })
+                    .map(|read| read.id)
+                    .collect();
+                self.set_ids(ids);
+            }
+            None => {}
+        }
+    }
+
+    /// Takes the selected story out of the read history.
+    fn forget_selected(&mut self) {
+        let Some(&id) = self.ids.get(self.selected as usize) else {
+            return;
+        };
+        if let Err(e) = self.readed_history.forget(id) {
+            let _ = self.tx_error.send(AppError::new(format!(
+                "Could not remove the story from read history: {}",
+                e
+            )));
+        }
+        let (selected, top) = (self.selected, self.list_top_cursor);
+        self.list_collection();
+
+        // The next story moves up into the removed one's place.
+        if let Some(last) = self.ids.len().checked_sub(1) {
+            self.selected = selected.min(last as u16);
+            self.list_top_cursor = top.min(self.selected);
+            self.load_more();
+        }
+    }
+
+    fn clear_history(&mut self) {
+        if let Err(e) = self.readed_history.clear() {
+            let _ = self.tx_error.send(AppError::new(format!(
+                "Could not clear read history: {}",
+                e
+            )));
+        }
+        self.list_collection();
+    }
+
+    pub fn is_saved(&self, id: usize) -> bool {
+        self.bookmarks.contains(id)
+    }
+
+    /// Saves `story` for later, or forgets it if it was saved. Returns
+    /// whether it is saved now.
+    pub fn toggle_bookmark(&mut self, story: &ItemResponse) -> Result<bool> {
+        let saved = self.bookmarks.toggle(Bookmark {
+            id: story.id,
+            title: story.title.clone().unwrap_or("No title".to_string()),
+            url: story.url.clone(),
+            saved_at: Utc::now().timestamp(),
+            tags: Vec::new(),
+        })?;
+        if matches!(self.collection, Some(Collection::Saved { .. })) {
+            self.list_collection();
+        }
+        Ok(saved)
+    }
+
+    fn edit_tags(&mut self, key: KeyEvent) {
+        let Some(input) = self.tags_input.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.tags_input = None,
+            KeyCode::Enter => {
+                let tags = bookmarks::parse_tags(&self.tags_input.take().unwrap_or_default());
+                let Some(&id) = self.ids.get(self.selected as usize) else {
+                    return;
+                };
+                if let Err(e) = self.bookmarks.set_tags(id, tags) {
+                    let _ = self
+                        .tx_error
+                        .send(AppError::new(format!("Could not save the tags: {}", e)));
+                }
+                // The story may no longer have the tag being listed.
+                self.list_collection();
+            }
+            _ => {}
+        }
+    }
+
+    /// The query whose results are shown, if any.
+    pub fn search_query(&self) -> Option<&str> {
+        self.search.as_ref().map(|search| search.query.as_str())
+    }
+
+    /// Shows a page of results for `query`, appending to the earlier pages.
+    /// Hits among `ids` missing from `items` are shown as placeholders.
+    pub fn set_search_results(
+        &mut self,
+        query: &str,
+        page: usize,
+        ids: Vec<usize>,
+        items: Vec<ItemResponse>,
+        has_more: bool,
+    ) {
+        let Some(search) = self.search.as_mut().filter(|search| search.query == query) else {
+            return;
+        };
+        search.page = page;
+        search.has_more = has_more;
+
+        if page == 0 {
+            let selected_id = self.ids.get(self.selected as usize).copied();
+            self.ids.clear();
+            self.items.clear();
+            self.stale.clear();
+            // Refreshed results keep the selected story selected.
+            match selected_id.and_then(|id| ids.iter().position(|&hit| hit == id)) {
+                Some(position) => {
+                    self.selected = position as u16;
+                    if self.selected < self.list_top_cursor {
+                        self.list_top_cursor = self.selected;
+                    } else if self.height > 0 && self.selected >= self.list_top_cursor + self.height
+                    {
+                        self.list_top_cursor = self.selected + 1 - self.height;
+                    }
+                }
+                None => {
+                    self.selected = 0;
+                    self.list_top_cursor = 0;
+                }
+            }
+        }
+        // Results come loaded, and the retry of those that failed fills them
+        // in, so there is nothing left to request.
+        self.ids.extend(ids);
+        self.requested = self.ids.len();
+        self.items
+            .extend(items.into_iter().map(|item| (item.id, item)));
+        self.loading.set_loading(false);
+    }
+
+    fn edit_search(&mut self, key: KeyEvent) {
+        let Some(input) = self.search_input.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.search_input = None,
+            KeyCode::Enter if self.collection.is_some() => {
+                let filter = self.search_input.take().unwrap_or_default();
+                let filter = filter.trim();
+                let filter = (!filter.is_empty()).then(|| filter.to_string());
+                let collection = match self.collection {
+                    Some(Collection::Saved { .. }) => Collection::Saved { tag: filter },
+                    _ => Collection::History { query: filter },
+                };
+                self.show_collection(collection);
+            }
+            KeyCode::Enter => {
+                let query = self.search_input.take().unwrap_or_default();
+                let query = query.trim();
+                if !query.is_empty() {
+                    self.search = Some(Search {
+                        query: query.to_string(),
+                        page: 0,
+                        has_more: false,
+                    });
+                    self.loading.set_loading(true);
+                    let _ = self.tx_action.send(AppAction::Search {
+                        query: query.to_string(),
+                        page: 0,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Asks for the next page once the selection reaches the last result.
+    fn load_more_results(&mut self) {
+        let at_end = self.selected as usize + 1 >= self.ids.len();
+        if let Some(search) = &self.search
+            && search.has_more
+            && at_end
+            && !self.loading.is_loading()
+        {
+            self.loading.set_loading(true);
+            let _ = self.tx_action.send(AppAction::Search {
+                query: search.query.clone(),
+                page: search.page + 1,
+            });
         }
     }
 
@@ -45,9 +486,34 @@ impl ListBlock {
         self.loading.is_loading()
     }
 
+    pub fn start_loading(&mut self) {
+        self.loading.set_loading(true);
+    }
+
+    /// Stops the spinner after a request for the list failed.
+    pub fn stop_loading(&mut self) {
+        self.loading.set_loading(false);
+    }
+
     fn next_topic(&mut self) {
+        match self.collection.take() {
+            Some(Collection::Saved { .. }) => {
+                self.show_collection(Collection::History { query: None });
+                return;
+            }
+            Some(Collection::History { .. }) => {
+                self.topic = StoryType::Show;
+                return;
+            }
+            None if self.topic == StoryType::New => {
+                self.show_collection(Collection::Saved { tag: None });
+                return;
+            }
+            None => {}
+        }
         self.topic = match self.topic {
-            StoryType::Show => StoryType::Best,
+            StoryType::Show => StoryType::Ask,
+            StoryType::Ask => StoryType::Best,
             StoryType::Best => StoryType::Jobs,
             StoryType::Jobs => StoryType::Top,
             StoryType::Top => StoryType::New,
@@ -56,39 +522,125 @@ impl ListBlock {
     }
 
     fn prev_topic(&mut self) {
+        match self.collection.take() {
+            Some(Collection::Saved { .. }) => {
+                self.topic = StoryType::New;
+                return;
+            }
+            Some(Collection::History { .. }) => {
+                self.show_collection(Collection::Saved { tag: None });
+                return;
+            }
+            None if self.topic == StoryType::Show => {
+                self.show_collection(Collection::History { query: None });
+                return;
+            }
+            None => {}
+        }
         self.topic = match self.topic {
             StoryType::Show => StoryType::New,
             StoryType::New => StoryType::Top,
             StoryType::Top => StoryType::Jobs,
             StoryType::Jobs => StoryType::Best,
-            StoryType::Best => StoryType::Show,
+            StoryType::Best => StoryType::Ask,
+            StoryType::Ask => StoryType::Show,
         };
     }
 
-    pub fn set_data(&mut self, data: Vec<ItemResponse>) {
-        self.data = data;
+    pub fn reset(&mut self) {
+        self.loading.set_loading(true);
+        self.ids.clear();
+        self.items.clear();
+        self.stale.clear();
+        self.requested = 0;
         self.selected = 0;
-        self.list_top_cursor = 0;
-        self.loading.set_loading(false);
     }
 
-    pub fn reset(&mut self) {
+    /// Reloads the feed, or the search results, bypassing the cache. The
+    /// selected story stays selected if it is still listed.
+    pub fn refresh(&mut self) {
         self.loading.set_loading(true);
-        self.data.clear();
-        self.selected = 0;
+        if let Some(search) = &self.search {
+            let _ = self
+                .tx_action
+                .send(AppAction::Reload(Box::new(AppAction::Search {
+                    query: search.query.clone(),
+                    page: 0,
+                })));
+            return;
+        }
+        if self.collection.is_some() {
+            let _ = self
+                .tx_action
+                .send(AppAction::Reload(Box::new(AppAction::StoryItems(
+                    self.ids.clone(),
+                ))));
+            return;
+        }
+
+        // The feed first: loading it cancels the row requests in flight.
+        let _ = self
+            .tx_action
+            .send(AppAction::Reload(Box::new(AppAction::Story(self.topic))));
+        let loaded = self
+            .ids
+            .iter()
+            .filter(|id| self.items.contains_key(id))
+            .copied()
+            .collect::<Vec<_>>();
+        if !loaded.is_empty() {
+            let _ = self
+                .tx_action
+                .send(AppAction::Reload(Box::new(AppAction::StoryItems(loaded))));
+        }
+    }
+
+    /// Records a visit to the comments of `story`, returning the previous
+    /// one.
+    pub fn visit_thread(&mut self, story: &ItemResponse) -> Result<Option<Visit>> {
+        let comments = story.descendants.unwrap_or(0);
+        self.visits
+            .visit(story.id, comments, Utc::now().timestamp())
+    }
+
+    /// Notes that the comments of `story_id` up to `max_id` were loaded.
+    pub fn see_comments(&mut self, story_id: usize, max_id: usize) {
+        self.visits.see(story_id, max_id);
+    }
+
+    /// Deletes the files the list keeps its state in.
+    #[cfg(test)]
+    pub fn remove_storage(&mut self) {
+        let _ = self.readed_history.remove();
+        let _ = self.visits.remove();
+        let _ = self.bookmarks.remove();
+    }
+
+    /// Writes the comments seen since the last save.
+    pub fn save_seen_comments(&mut self) -> Result<()> {
+        self.visits.save_seen()
     }
 
     pub fn set_read(&mut self) -> Result<()> {
-        let current_item = self.data.get(self.selected as usize).unwrap();
-        self.readed_history
-            .add_read_item(self.topic, current_item.id)?;
+        if let Some(&id) = self.ids.get(self.selected as usize) {
+            let title = self.items.get(&id).and_then(|item| item.title.clone());
+            self.readed_history
+                .add_read_item(id, title, Utc::now().timestamp())?;
+        }
+        // Opened again, it moves up to today.
+        if matches!(self.collection, Some(Collection::History { .. })) {
+            self.list_collection();
+        }
         Ok(())
     }
 }
 
 impl DrawableComponent for ListBlock {
     fn draw(&mut self, f: &mut Frame, rect: Rect) -> Result<()> {
-        self.height = rect.height.saturating_sub(2);
+        let history = matches!(self.collection, Some(Collection::History { .. }));
+        // In stories, not lines. In the history each may come under a day.
+        let row_height = self.row_format.height() + history as usize;
+        self.height = rect.height.saturating_sub(2) / row_height.max(1) as u16;
         let left_block = Block::bordered()
             .border_type(BorderType::Rounded)
             .border_style({
@@ -98,35 +650,126 @@ impl DrawableComponent for ListBlock {
                     Style::new()
                 }
             })
-            .title(Title::from(Line::from(vec![
-                self.loading.to_span_mut().unwrap_or(Span::raw("")),
-                Span::raw("<"),
-                Span::styled("S-T", Style::default().fg(Color::Red)),
-                Span::raw(format!(
-                    " {}({}/{}) ",
-                    self.topic,
+            .title(Title::from(Line::from({
+                let position = format!(
+                    "({}/{})",
                     self.selected
-                        .saturating_add(if self.data.is_empty() { 0 } else { 1 }),
-                    self.data.len()
-                )),
-                Span::styled("T", Style::default().fg(Color::Red)),
-                Span::raw(">"),
-            ])));
+                        .saturating_add(if self.ids.is_empty() { 0 } else { 1 }),
+                    self.ids.len()
+                );
+                let loading = self.loading.to_span_mut().unwrap_or(Span::raw(""));
+                if self.confirm_clear {
+                    vec![Span::styled(
+                        "Clear the whole read history? y/N",
+                        Style::default().fg(Color::Red),
+                    )]
+                } else if let Some(input) = &self.tags_input {
+                    vec![
+                        loading,
+                        Span::styled("Tags: ", Style::default().fg(Color::Red)),
+                        Span::raw(format!("{}_", input)),
+                    ]
+                } else if let Some(input) = &self.search_input {
+                    vec![
+                        loading,
+                        Span::styled("/", Style::default().fg(Color::Red)),
+                        Span::raw(format!("{}_", input)),
+                    ]
+                } else if let Some(search) = &self.search {
+                    vec![
+                        loading,
+                        Span::raw(format!(" Search: {}{} ", search.query, position)),
+                        Span::styled("Esc", Style::default().fg(Color::Red)),
+                    ]
+                } else {
+                    let topic = match &self.collection {
+                        Some(Collection::Saved { tag: Some(tag) }) => format!("Saved #{}", tag),
+                        Some(Collection::Saved { tag: None }) => "Saved".to_string(),
+                        Some(Collection::History { query: Some(query) }) => {
+                            format!("History: {}", query)
+                        }
+                        Some(Collection::History { query: None }) => "History".to_string(),
+                        None => self.topic.to_string(),
+                    };
+                    vec![
+                        loading,
+                        Span::raw("<"),
+                        Span::styled("S-T", Style::default().fg(Color::Red)),
+                        Span::raw(format!(" {}{} ", topic, position)),
+                        Span::styled("T", Style::default().fg(Color::Red)),
+                        Span::raw(">"),
+                    ]
+                }
+            })));
+        let left_block = match &self.collection {
+            Some(Collection::Saved { .. }) => {
+                let tags = self.bookmarks.tags();
+                left_block.title_bottom(Line::from(vec![
+                    Span::styled("/", Style::default().fg(Color::Red)),
+                    Span::raw(" tag "),
+                    Span::styled("t", Style::default().fg(Color::Red)),
+                    Span::raw(" edit tags"),
+                    Span::raw(if tags.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" ({})", tags.join(", "))
+                    }),
+                ]))
+            }
+            Some(Collection::History { .. }) => left_block.title_bottom(Line::from(vec![
+                Span::styled("/", Style::default().fg(Color::Red)),
+                Span::raw(" search "),
+                Span::styled("d", Style::default().fg(Color::Red)),
+                Span::raw(" remove "),
+                Span::styled("D", Style::default().fg(Color::Red)),
+                Span::raw(" clear"),
+            ])),
+            None => left_block,
+        };
 
+        let now = Utc::now();
+        // The first row in view names its day even if the one above it had
+        // the same.
+        let mut last_day = None;
         let list_items = self
-            .data
+            .ids
             .iter()
             .skip(self.list_top_cursor as usize)
             .take(self.height as usize)
             .enumerate()
-            .map(|(idx, item)| {
-                let is_readed = self.readed_history.id_is_readed(self.topic, item.id);
-                let list_item = ListItem::new(item.title.clone().unwrap_or("No title".to_string()));
+            .map(|(idx, id)| {
+                let rank = idx + self.list_top_cursor as usize + 1;
+                let is_readed = self.readed_history.id_is_readed(*id);
+                let item = self.items.get(id);
+                let lines = match item {
+                    Some(item) => self.row_format.render(rank, item, now),
+                    None => self.row_format.placeholder(rank),
+                };
+                let mut lines = lines.into_iter().map(Line::from).collect::<Vec<_>>();
+                if history && let Some(at) = self.readed_history.read_at(*id) {
+                    let day = day_label(at, now.with_timezone(&Local));
+                    if last_day.as_ref() != Some(&day) {
+                        lines.insert(0, Line::from(day.clone()).bold().fg(Color::Yellow));
+                        last_day = Some(day);
+                    }
+                }
+                let new_comments = item.map_or(0, |item| {
+                    self.visits
+                        .new_comments(item.id, item.descendants.unwrap_or(0))
+                });
+                if new_comments > 0 {
+                    lines[0].push_span(Span::raw(format!(" {} new", new_comments)).yellow());
+                }
+                let saved_view = matches!(self.collection, Some(Collection::Saved { .. }));
+                if !saved_view && self.bookmarks.contains(*id) {
+                    lines[0].push_span(Span::raw(" ★").yellow());
+                }
+                let list_item = ListItem::new(lines);
                 let mut style = Style::default().fg(Color::White);
 
-                if idx + self.list_top_cursor as usize == self.selected as usize {
+                if rank - 1 == self.selected as usize {
                     style = style.bg(Color::Blue);
-                } else if is_readed {
+                } else if (is_readed && !history) || item.is_none() {
                     style = style.fg(Color::DarkGray);
                 }
 
@@ -143,35 +786,454 @@ impl DrawableComponent for ListBlock {
 
 impl Component for ListBlock {
     fn event(&mut self, key: KeyEvent) {
-        if key.code == KeyCode::Char('j') {
+        if self.confirm_clear {
+            self.confirm_clear = false;
+            if key.code == KeyCode::Char('y') {
+                self.clear_history();
+            }
+            return;
+        }
+        if self.tags_input.is_some() {
+            self.edit_tags(key);
+            return;
+        }
+        if self.is_editing() {
+            self.edit_search(key);
+            return;
+        }
+
+        if key.code == KeyCode::Char('/') {
+            self.search_input = Some(String::new());
+        } else if key.code == KeyCode::Esc && self.search.is_some() {
+            self.search = None;
+        } else if key.code == KeyCode::Esc
+            && let Some(collection) = &mut self.collection
+        {
+            // Lists the whole collection again.
+            *collection = match collection {
+                Collection::Saved { .. } => Collection::Saved { tag: None },
+                Collection::History { .. } => Collection::History { query: None },
+            };
+            self.list_collection();
+        } else if key.code == KeyCode::Char('H') {
+            self.search = None;
+            self.show_collection(Collection::History { query: None });
+        } else if matches!(self.collection, Some(Collection::History { .. }))
+            && key.code == KeyCode::Char('d')
+        {
+            self.forget_selected();
+        } else if matches!(self.collection, Some(Collection::History { .. }))
+            && key.code == KeyCode::Char('D')
+        {
+            self.confirm_clear = true;
+        } else if key.code == KeyCode::Char('t')
+            && matches!(self.collection, Some(Collection::Saved { .. }))
+        {
+            if let Some(bookmark) = self
+                .ids
+                .get(self.selected as usize)
+                .and_then(|&id| self.bookmarks.get(id))
+            {
+                self.tags_input = Some(bookmark.tags.join(", "));
+            }
+        } else if key.code == KeyCode::Char('r') {
+            self.refresh();
+        } else if key.code == KeyCode::Char('j') {
             self.selected = self
                 .selected
                 .saturating_add(1)
-                .min(self.data.len().saturating_sub(1) as u16);
+                .min(self.ids.len().saturating_sub(1) as u16);
             if self.selected >= self.list_top_cursor + self.height {
                 self.list_top_cursor = self.list_top_cursor.saturating_add(1);
             }
+            self.load_more();
         } else if key.code == KeyCode::Char('k') {
             self.selected = self.selected.saturating_sub(1);
             if self.selected < self.list_top_cursor {
                 self.list_top_cursor = self.list_top_cursor.saturating_sub(1);
             }
         } else if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('b') {
-            self.selected = self.selected.saturating_sub(self.height - 1);
-            self.list_top_cursor = self.selected.min(self.data.len().saturating_sub(1) as u16);
+            self.selected = self.selected.saturating_sub(self.page_step());
+            self.list_top_cursor = self.selected.min(self.ids.len().saturating_sub(1) as u16);
         } else if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('f') {
             self.selected = self
                 .selected
-                .saturating_add(self.height - 1)
-                .min(self.data.len().saturating_sub(1) as u16);
+                .saturating_add(self.page_step())
+                .min(self.ids.len().saturating_sub(1) as u16);
             self.list_top_cursor = self
                 .selected
-                .saturating_sub(self.height - 1)
-                .min(self.data.len().saturating_sub(1) as u16);
+                .saturating_sub(self.page_step())
+                .min(self.ids.len().saturating_sub(1) as u16);
+            self.load_more();
         } else if key.code == KeyCode::Tab {
+            self.search = None;
             self.next_topic();
         } else if key.code == KeyCode::BackTab {
+            self.search = None;
             self.prev_topic();
         }
     }
 }
+
+/// What is known about a saved story before it is fetched again. The rest,
+/// its post time included, stays unknown.
+fn bookmark_item(bookmark: Bookmark) -> ItemResponse {
+    ItemResponse {
+        id: bookmark.id,
+        deleted: None,
+        kind: ItemType::Story,
+        by: None,
+        time: 0,
+        text: None,
+        dead: None,
+        parent: None,
+        poll: None,
+        kids: None,
+        children: None,
+        url: bookmark.url,
+        score: None,
+        title: Some(bookmark.title),
+        parts: None,
+        descendants: None,
+    }
+}
+
+/// The day `at` (Unix seconds) falls on, e.g. `Today` or `Mon 3 Mar 2025`.
+fn day_label(at: i64, now: DateTime<Local>) -> String {
+    let Some(day) = DateTime::from_timestamp(at, 0).map(|at| at.with_timezone(&Local).date_naive())
+    else {
+        return "Unknown day".to_string();
+    };
+    let today = now.date_naive();
+    if day == today {
+        "Today".to_string()
+    } else if today.pred_opt() == Some(day) {
+        "Yesterday".to_string()
+    } else {
+        day.format("%a %-d %b %Y").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::{Deref, DerefMut};
+
+    use chrono::TimeZone;
+    use ratatui::crossterm::event::KeyModifiers;
+    use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+
+    use super::*;
+
+    /// A list whose storage files are removed once the test ends, even when
+    /// it fails.
+    struct TestList(ListBlock);
+
+    impl Deref for TestList {
+        type Target = ListBlock;
+
+        fn deref(&self) -> &ListBlock {
+            &self.0
+        }
+    }
+
+    impl DerefMut for TestList {
+        fn deref_mut(&mut self) -> &mut ListBlock {
+            &mut self.0
+        }
+    }
+
+    impl Drop for TestList {
+        fn drop(&mut self) {
+            self.0.remove_storage();
+        }
+    }
+
+    fn test_list() -> (TestList, UnboundedReceiver<AppAction>) {
+        let (tx_action, rx_action) = unbounded_channel();
+        let (tx_error, _rx_error) = unbounded_channel();
+        let list = ListBlock::new(
+            &Environment {
+                tx_action,
+                tx_error,
+            },
+            true,
+        );
+        (TestList(list), rx_action)
+    }
+
+    #[test]
+    fn test_topic_cycle_reaches_ask() {
+        let (mut list, _rx_action) = test_list();
+        assert_eq!(list.topic, StoryType::Show);
+
+        list.next_topic();
+        assert_eq!(list.topic, StoryType::Ask);
+        list.next_topic();
+        assert_eq!(list.topic, StoryType::Best);
+
+        list.prev_topic();
+        assert_eq!(list.topic, StoryType::Ask);
+        list.prev_topic();
+        assert_eq!(list.topic, StoryType::Show);
+    }
+
+    #[test]
+    fn test_search_prompt() {
+        let (mut list, mut rx_action) = test_list();
+        let press =
+            |list: &mut ListBlock, code| list.event(KeyEvent::new(code, KeyModifiers::NONE));
+
+        press(&mut list, KeyCode::Char('/'));
+        assert!(list.is_editing());
+        for c in "rustx".chars() {
+            press(&mut list, KeyCode::Char(c));
+        }
+        press(&mut list, KeyCode::Backspace);
+        press(&mut list, KeyCode::Enter);
+
+        assert!(!list.is_editing());
+        assert_eq!(list.search_query(), Some("rust"));
+        assert_eq!(
+            rx_action.try_recv(),
+            Ok(AppAction::Search {
+                query: "rust".to_string(),
+                page: 0
+            })
+        );
+
+        // A hit that failed to load waits for its retry as a placeholder.
+        let story = |id| ItemResponse {
+            id,
+            ..ItemResponse::default()
+        };
+        list.set_search_results("rust", 0, vec![5, 6], vec![story(5)], false);
+        assert_eq!(list.ids, vec![5, 6]);
+        press(&mut list, KeyCode::Char('j'));
+        assert!(list.selected_item().is_none());
+        list.add_items(vec![story(6)]);
+        assert_eq!(list.selected_item().map(|item| item.id), Some(6));
+
+        press(&mut list, KeyCode::Esc);
+        assert_eq!(list.search_query(), None);
+    }
+
+    #[test]
+    fn test_pages_rows_in_rank_order() {
+        let (mut list, mut rx_action) = test_list();
+        list.height = 20;
+
+        list.set_ids((1..=100).collect());
+        assert_eq!(
+            rx_action.try_recv(),
+            Ok(AppAction::StoryItems((1..=30).collect()))
+        );
+        assert!(list.selected_item().is_none());
+
+        let story = |id| ItemResponse {
+            id,
+            ..ItemResponse::default()
+        };
+        list.add_items(vec![story(2), story(1), story(500)]);
+        assert_eq!(list.selected_item().map(|item| item.id), Some(1));
+        assert_eq!(list.items.len(), 2);
+
+        for _ in 0..20 {
+            list.event(KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE));
+        }
+        assert_eq!(
+            rx_action.try_recv(),
+            Ok(AppAction::StoryItems((31..=60).collect()))
+        );
+
+        // A refreshed feed keeps the selection and what was already loaded,
+        // and only asks for the rows still missing.
+        list.set_ids((0..=100).collect());
+        assert_eq!(list.selected_item(), None);
+        assert_eq!(list.ids[list.selected as usize], 21);
+        let missing = (0..60).filter(|id| ![1, 2].contains(id)).collect();
+        assert_eq!(rx_action.try_recv(), Ok(AppAction::StoryItems(missing)));
+
+        // Rows reported as changed are fetched again.
+        list.add_items((3..60).map(story).collect());
+        list.invalidate(&[2, 500]);
+        list.set_ids((0..=100).collect());
+        assert_eq!(rx_action.try_recv(), Ok(AppAction::StoryItems(vec![0, 2])));
+    }
+
+    #[test]
+    fn test_paging_without_room() {
+        let (mut list, _rx_action) = test_list();
+        let press = |list: &mut ListBlock, code| {
+            list.event(KeyEvent::new(code, KeyModifiers::CONTROL));
+        };
+        list.set_ids((1..=100).collect());
+
+        // Nothing fits yet, so pages are a single row.
+        assert_eq!(list.height, 0);
+        press(&mut list, KeyCode::Char('f'));
+        assert_eq!(list.selected, 1);
+        press(&mut list, KeyCode::Char('b'));
+        assert_eq!(list.selected, 0);
+
+        list.height = 20;
+        press(&mut list, KeyCode::Char('f'));
+        assert_eq!(list.selected, 19);
+    }
+
+    #[test]
+    fn test_refresh_reloads_feed_and_loaded_rows() {
+        let (mut list, mut rx_action) = test_list();
+        list.topic = StoryType::Top;
+        list.set_ids(vec![1, 2, 3]);
+        let _ = rx_action.try_recv();
+        list.add_items(vec![ItemResponse {
+            id: 2,
+            ..ItemResponse::default()
+        }]);
+
+        list.event(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE));
+        assert!(list.is_loading());
+        assert_eq!(
+            rx_action.try_recv(),
+            Ok(AppAction::Reload(Box::new(AppAction::Story(
+                StoryType::Top
+            ))))
+        );
+        assert_eq!(
+            rx_action.try_recv(),
+            Ok(AppAction::Reload(Box::new(AppAction::StoryItems(vec![2]))))
+        );
+    }
+
+    #[test]
+    fn test_saved_topic() {
+        let (mut list, mut rx_action) = test_list();
+        let press =
+            |list: &mut ListBlock, code| list.event(KeyEvent::new(code, KeyModifiers::NONE));
+        let story = |id| ItemResponse {
+            id,
+            title: Some(format!("Story {}", id)),
+            ..ItemResponse::default()
+        };
+        assert!(list.toggle_bookmark(&story(1)).unwrap());
+        assert!(list.toggle_bookmark(&story(2)).unwrap());
+
+        // Saved comes between New and Show, and shows what is known until
+        // the fresh copies arrive.
+        list.topic = StoryType::New;
+        press(&mut list, KeyCode::Tab);
+        assert!(list.is_showing_collection());
+        assert_eq!(list.topic, StoryType::New);
+        assert_eq!(list.ids, vec![2, 1]);
+        let placeholder = list.selected_item().unwrap();
+        assert_eq!(placeholder.title.as_deref(), Some("Story 2"));
+        assert_eq!(
+            (placeholder.by.as_ref(), placeholder.text.as_ref()),
+            (None, None)
+        );
+        assert_eq!(placeholder.time, 0);
+        assert_eq!(rx_action.try_recv(), Ok(AppAction::StoryItems(vec![2, 1])));
+
+        // Tag the selected story, then list only that tag.
+        press(&mut list, KeyCode::Char('t'));
+        for c in "rust".chars() {
+            press(&mut list, KeyCode::Char(c));
+        }
+        press(&mut list, KeyCode::Enter);
+        press(&mut list, KeyCode::Char('/'));
+        for c in "rust".chars() {
+            press(&mut list, KeyCode::Char(c));
+        }
+        press(&mut list, KeyCode::Enter);
+        assert_eq!(list.ids, vec![2]);
+        press(&mut list, KeyCode::Esc);
+        assert_eq!(list.ids, vec![2, 1]);
+
+        // Forgetting a story takes it off the list.
+        assert!(!list.toggle_bookmark(&story(2)).unwrap());
+        assert_eq!(list.ids, vec![1]);
+
+        // Then comes the history, then Show.
+        press(&mut list, KeyCode::Tab);
+        assert!(matches!(
+            list.collection,
+            Some(Collection::History { query: None })
+        ));
+        press(&mut list, KeyCode::Tab);
+        assert!(!list.is_showing_collection());
+        assert_eq!(list.topic, StoryType::Show);
+    }
+
+    #[test]
+    fn test_history() {
+        let (mut list, _rx_action) = test_list();
+        let press =
+            |list: &mut ListBlock, code| list.event(KeyEvent::new(code, KeyModifiers::NONE));
+        for (id, title) in [
+            (1, "Rust 2024"),
+            (2, "Show HN: A TUI"),
+            (3, "Rust in Linux"),
+        ] {
+            list.readed_history
+                .add_read_item(id, Some(title.to_string()), id as i64)
+                .unwrap();
+        }
+
+        press(&mut list, KeyCode::Char('H'));
+        assert_eq!(list.ids, vec![3, 2, 1]);
+
+        // Searches the titles.
+        press(&mut list, KeyCode::Char('/'));
+        for c in "RUST".chars() {
+            press(&mut list, KeyCode::Char(c));
+        }
+        press(&mut list, KeyCode::Enter);
+        assert_eq!(list.ids, vec![3, 1]);
+        press(&mut list, KeyCode::Esc);
+        assert_eq!(list.ids, vec![3, 2, 1]);
+
+        // Opening a story again brings it to the top.
+        press(&mut list, KeyCode::Char('j'));
+        list.set_read().unwrap();
+        assert_eq!(list.ids, vec![2, 3, 1]);
+        assert_eq!(list.selected, 0);
+
+        assert_eq!(
+            list.readed_history.reads().next().unwrap().title.as_deref(),
+            Some("Show HN: A TUI")
+        );
+
+        press(&mut list, KeyCode::Char('j'));
+        press(&mut list, KeyCode::Char('d'));
+        assert_eq!(list.ids, vec![2, 1]);
+        assert!(!list.readed_history.id_is_readed(3));
+        // The cursor stays in place, or on the last story.
+        assert_eq!(list.selected, 1);
+        press(&mut list, KeyCode::Char('d'));
+        assert_eq!(list.ids, vec![2]);
+        assert_eq!(list.selected, 0);
+
+        // Clearing asks first.
+        press(&mut list, KeyCode::Char('D'));
+        press(&mut list, KeyCode::Char('n'));
+        assert_eq!(list.ids, vec![2]);
+        press(&mut list, KeyCode::Char('D'));
+        assert!(list.is_editing());
+        press(&mut list, KeyCode::Char('y'));
+        assert!(list.ids.is_empty());
+    }
+
+    #[test]
+    fn test_day_label() {
+        let now = Local.with_ymd_and_hms(2025, 3, 4, 12, 0, 0).unwrap();
+        let at = |day, hour| {
+            Local
+                .with_ymd_and_hms(2025, 3, day, hour, 0, 0)
+                .unwrap()
+                .timestamp()
+        };
+        assert_eq!(day_label(at(4, 1), now), "Today");
+        assert_eq!(day_label(at(3, 23), now), "Yesterday");
+        assert_eq!(day_label(at(1, 12), now), "Sat 1 Mar 2025");
+    }
+}
@@ -1,7 +1,11 @@
 mod article;
 mod comment;
 mod list;
+mod profile;
+mod row_format;
 
 pub use article::Article;
 pub use comment::Comment;
 pub use list::ListBlock;
+pub use profile::Profile;
+pub use row_format::RowFormat;
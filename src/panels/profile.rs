@@ -0,0 +1,263 @@
+use std::io::Result;
+
+use chrono::{DateTime, Utc};
+use hackernews::{
+    get_items::{ItemResponse, ItemType},
+    get_user::User,
+};
+use html2text::config;
+use ratatui::{
+    Frame,
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, BorderType, List, ListItem, Paragraph, Wrap},
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    AppAction,
+    app::Environment,
+    components::{Component, DrawableComponent, Loading},
+};
+
+/// Number of submitted items requested at once.
+const PAGE_SIZE: usize = 20;
+
+pub struct Profile {
+    pub user_id: String,
+    user: Option<User>,
+    /// Loaded submissions, newest first. Deleted ones are left out.
+    items: Vec<ItemResponse>,
+    /// How many ids of `submitted` have been requested so far.
+    requested: usize,
+    selected: usize,
+    list_top_cursor: usize,
+    height: usize,
+    loading: Loading,
+    tx_action: UnboundedSender<AppAction>,
+}
+
+impl Profile {
+    pub fn new(env: &Environment, user_id: String) -> Self {
+        Self {
+            user_id,
+            user: None,
+            items: Vec::new(),
+            requested: 0,
+            selected: 0,
+            list_top_cursor: 0,
+            height: 0,
+            loading: Loading::new(),
+            tx_action: env.tx_action.clone(),
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading.is_loading()
+    }
+
+    /// Stops the spinner after a request for the profile failed.
+    pub fn stop_loading(&mut self) {
+        self.loading.set_loading(false);
+    }
+
+    pub fn set_user(&mut self, user: User) {
+        if user.id != self.user_id {
+            return;
+        }
+        self.user = Some(user);
+        self.loading.set_loading(false);
+        self.load_more();
+    }
+
+    pub fn add_submitted(&mut self, user_id: &str, items: Vec<ItemResponse>) {
+        if user_id != self.user_id {
+            return;
+        }
+        self.items
+            .extend(items.into_iter().filter(|item| !item.is_deleted()));
+        self.loading.set_loading(false);
+    }
+
+    /// The submission under the cursor.
+    pub fn selected_item(&self) -> Option<&ItemResponse> {
+        self.items.get(self.selected)
+    }
+
+    /// Requests the next page of submissions, if any are left.
+    fn load_more(&mut self) {
+        let Some(user) = &self.user else {
+            return;
+        };
+        if self.loading.is_loading() || self.requested >= user.submitted.len() {
+            return;
+        }
+
+        let ids = user
+            .submitted
+            .iter()
+            .skip(self.requested)
+            .take(PAGE_SIZE)
+            .map(|&id| id as usize)
+            .collect::<Vec<_>>();
+        self.requested += ids.len();
+        self.loading.set_loading(true);
+        let _ = self.tx_action.send(AppAction::Submitted {
+            user: self.user_id.clone(),
+            ids,
+        });
+    }
+
+    fn summary(&self, width: usize) -> String {
+        let Some(user) = &self.user else {
+            return "Loading profile...".to_string();
+        };
+
+        let about = user
+            .about
+            .as_deref()
+            .map(|about| {
+                config::plain()
+                    .link_footnotes(false)
+                    .no_link_wrapping()
+                    .string_from_read(about.as_bytes(), width.max(1))
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default();
+        format!(
+            "User: {}\nKarma: {}\nMember for: {}\nSubmissions: {}\n\n{}",
+            user.id,
+            user.karma,
+            account_age(user.created, Utc::now()),
+            user.submitted.len(),
+            about.trim_end()
+        )
+    }
+}
+
+/// Rough age of an account created at `created` (Unix seconds).
+fn account_age(created: u64, now: DateTime<Utc>) -> String {
+    let days = now.timestamp().saturating_sub(created as i64) / (60 * 60 * 24);
+    match days {
+        0 => "less than a day".to_string(),
+        1 => "1 day".to_string(),
+        2..365 => format!("{} days", days),
+        365..730 => "1 year".to_string(),
+        _ => format!("{} years", days / 365),
+    }
+}
+
+/// One line per submission: the title of a story, or the start of a comment.
+fn item_label(item: &ItemResponse) -> String {
+    match item.kind {
+        ItemType::Comment => {
+            let text = config::plain()
+                .string_from_read(item.text.as_deref().unwrap_or("").as_bytes(), usize::MAX)
+                .unwrap_or_default();
+            format!(
+                "Comment: {}",
+                text.split_whitespace().collect::<Vec<_>>().join(" ")
+            )
+        }
+        _ => format!(
+            "{} ({} points)",
+            item.title.as_deref().unwrap_or("No title"),
+            item.score.unwrap_or(0)
+        ),
+    }
+}
+
+impl DrawableComponent for Profile {
+    fn draw(&mut self, f: &mut Frame, rect: Rect) -> Result<()> {
+        let [top, bottom] =
+            Layout::vertical([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(rect);
+        self.height = bottom.height.saturating_sub(2) as usize;
+
+        let summary = Paragraph::new(self.summary(top.width.saturating_sub(2) as usize))
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::bordered()
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::new().blue())
+                    .title("Profile")
+                    .title_bottom(Line::from(vec![
+                        Span::styled("Esc", Style::default().fg(Color::Red)),
+                        Span::raw(" back "),
+                        Span::styled("Enter", Style::default().fg(Color::Red)),
+                        Span::raw(" open"),
+                    ])),
+            );
+        f.render_widget(summary, top);
+
+        let submitted = self
+            .items
+            .iter()
+            .enumerate()
+            .skip(self.list_top_cursor)
+            .take(self.height)
+            .map(|(idx, item)| {
+                let style = if idx == self.selected {
+                    Style::default().bg(Color::Blue)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(item_label(item)).style(style)
+            })
+            .collect::<Vec<_>>();
+        let title = Line::from(vec![
+            self.loading.to_span_mut().unwrap_or(Span::raw("")),
+            Span::raw(format!(
+                "Submitted({}/{})",
+                self.items.len(),
+                self.user.as_ref().map_or(0, |user| user.submitted.len())
+            )),
+        ]);
+        let list = List::new(submitted).block(
+            Block::bordered()
+                .border_type(BorderType::Rounded)
+                .title(title),
+        );
+        f.render_widget(list, bottom);
+
+        Ok(())
+    }
+}
+
+impl Component for Profile {
+    fn event(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Char('j') {
+            self.selected = self
+                .selected
+                .saturating_add(1)
+                .min(self.items.len().saturating_sub(1));
+            if self.selected >= self.list_top_cursor + self.height {
+                self.list_top_cursor = self.list_top_cursor.saturating_add(1);
+            }
+            if self.selected + 1 >= self.items.len() {
+                self.load_more();
+            }
+        } else if key.code == KeyCode::Char('k') {
+            self.selected = self.selected.saturating_sub(1);
+            if self.selected < self.list_top_cursor {
+                self.list_top_cursor = self.selected;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_age() {
+        let now = DateTime::from_timestamp(1_000_000_000, 0).unwrap();
+        let day = 60 * 60 * 24;
+        assert_eq!(account_age(1_000_000_000 - 60, now), "less than a day");
+        assert_eq!(account_age(1_000_000_000 - 3 * day, now), "3 days");
+        assert_eq!(account_age(1_000_000_000 - 400 * day, now), "1 year");
+        assert_eq!(account_age(1_000_000_000 - 3650 * day, now), "10 years");
+    }
+}
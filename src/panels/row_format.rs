@@ -0,0 +1,272 @@
+use chrono::{DateTime, Utc};
+use hackernews::get_items::ItemResponse;
+
+/// Two lines per story: what it is, then how it is doing.
+const DEFAULT_FORMAT: &str =
+    "{rank}. {title}[ ({domain})]\\n[{points} points ][by {author} ]{age}[ | {comments} comments]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Rank,
+    Title,
+    Domain,
+    Points,
+    Comments,
+    Author,
+    Age,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "rank" => Some(Field::Rank),
+            "title" => Some(Field::Title),
+            "domain" => Some(Field::Domain),
+            "points" => Some(Field::Points),
+            "comments" => Some(Field::Comments),
+            "author" => Some(Field::Author),
+            "age" => Some(Field::Age),
+            _ => None,
+        }
+    }
+
+    /// The value for a story, empty if it has none.
+    fn value(&self, rank: usize, item: &ItemResponse, now: DateTime<Utc>) -> String {
+        match self {
+            Field::Rank => rank.to_string(),
+            Field::Title => item.title.clone().unwrap_or("No title".to_string()),
+            Field::Domain => item.domain().unwrap_or_default(),
+            Field::Points => item.score.map(|n| n.to_string()).unwrap_or_default(),
+            Field::Comments => item.descendants.map(|n| n.to_string()).unwrap_or_default(),
+            Field::Author => item.by.clone().unwrap_or_default(),
+            // Saved stories shown before they are fetched have no time.
+            Field::Age if item.time == 0 => String::new(),
+            Field::Age => relative_age(item.created_at(), now),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Field(Field),
+    /// Left out entirely when any of its fields is empty.
+    Optional(Vec<Segment>),
+}
+
+/// How a story is laid out in the list, from a template such as
+/// `{rank}. {title}[ ({domain})]\n{points} points`. Fields are written as
+/// `{name}`, `[...]` drops its contents when a field in it has no value, and
+/// `\n` starts the next line of the row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowFormat {
+    lines: Vec<Vec<Segment>>,
+}
+
+impl Default for RowFormat {
+    fn default() -> Self {
+        RowFormat::parse(DEFAULT_FORMAT).expect("The default row format is valid")
+    }
+}
+
+impl RowFormat {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let lines = template
+            .split("\\n")
+            .map(parse_line)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(RowFormat { lines })
+    }
+
+    /// Number of lines each story takes.
+    pub fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn render(&self, rank: usize, item: &ItemResponse, now: DateTime<Utc>) -> Vec<String> {
+        self.lines
+            .iter()
+            .map(|line| render_segments(line, false, rank, item, now).unwrap_or_default())
+            .collect()
+    }
+
+    /// The row of a story that has not been fetched yet.
+    pub fn placeholder(&self, rank: usize) -> Vec<String> {
+        let mut lines = vec![String::new(); self.height()];
+        lines[0] = format!("{}. Loading...", rank);
+        lines
+    }
+}
+
+fn parse_line(line: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut optional: Option<Vec<Segment>> = None;
+    let mut rest = line;
+
+    while let Some(c) = rest.chars().next() {
+        let segment = match c {
+            '{' => {
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| format!("Unclosed {{ in {}", line))?;
+                let name = &rest[1..end];
+                let field =
+                    Field::parse(name).ok_or_else(|| format!("Unknown field {{{}}}", name))?;
+                rest = &rest[end + 1..];
+                Segment::Field(field)
+            }
+            '[' => {
+                if optional.is_some() {
+                    return Err(format!("Nested [ in {}", line));
+                }
+                optional = Some(Vec::new());
+                rest = &rest[1..];
+                continue;
+            }
+            ']' => {
+                let inner = optional
+                    .take()
+                    .ok_or_else(|| format!("Unmatched ] in {}", line))?;
+                rest = &rest[1..];
+                Segment::Optional(inner)
+            }
+            _ => {
+                let end = rest.find(['{', '[', ']']).unwrap_or(rest.len());
+                let text = rest[..end].to_string();
+                rest = &rest[end..];
+                Segment::Text(text)
+            }
+        };
+        match &mut optional {
+            Some(inner) => inner.push(segment),
+            None => segments.push(segment),
+        }
+    }
+
+    if optional.is_some() {
+        return Err(format!("Unclosed [ in {}", line));
+    }
+    Ok(segments)
+}
+
+/// `None` if a field had no value and `strict` is set.
+fn render_segments(
+    segments: &[Segment],
+    strict: bool,
+    rank: usize,
+    item: &ItemResponse,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    let mut line = String::new();
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => line.push_str(text),
+            Segment::Field(field) => {
+                let value = field.value(rank, item, now);
+                if strict && value.is_empty() {
+                    return None;
+                }
+                line.push_str(&value);
+            }
+            Segment::Optional(inner) => {
+                if let Some(text) = render_segments(inner, true, rank, item, now) {
+                    line.push_str(&text);
+                }
+            }
+        }
+    }
+    Some(line)
+}
+
+/// How long ago `time` was, e.g. `3h ago`.
+pub(super) fn relative_age(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (now - time).num_minutes().max(0);
+    match minutes {
+        0 => "just now".to_string(),
+        1..60 => format!("{}m ago", minutes),
+        60..1440 => format!("{}h ago", minutes / 60),
+        1440..525_600 => format!("{}d ago", minutes / 1440),
+        _ => format!("{}y ago", minutes / 525_600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn story() -> ItemResponse {
+        ItemResponse {
+            id: 8863,
+            by: Some("dhouston".to_string()),
+            time: 1_000_000_000,
+            url: Some("http://www.getdropbox.com/u/2/screencast.html".to_string()),
+            score: Some(111),
+            title: Some("My YC app: Dropbox".to_string()),
+            descendants: Some(71),
+            ..ItemResponse::default()
+        }
+    }
+
+    #[test]
+    fn test_default_format() {
+        let now = DateTime::from_timestamp(1_000_000_000 + 3 * 60 * 60, 0).unwrap();
+        assert_eq!(
+            RowFormat::default().render(1, &story(), now),
+            vec![
+                "1. My YC app: Dropbox (getdropbox.com)",
+                "111 points by dhouston 3h ago | 71 comments"
+            ]
+        );
+
+        // Text posts and jobs leave out what they do not have.
+        let ask = ItemResponse {
+            url: None,
+            descendants: None,
+            ..story()
+        };
+        assert_eq!(
+            RowFormat::default().render(2, &ask, now),
+            vec!["2. My YC app: Dropbox", "111 points by dhouston 3h ago"]
+        );
+        let saved = ItemResponse {
+            by: None,
+            time: 0,
+            score: None,
+            ..ask
+        };
+        assert_eq!(
+            RowFormat::default().render(3, &saved, now),
+            vec!["3. My YC app: Dropbox", ""]
+        );
+        assert_eq!(
+            RowFormat::default().placeholder(3),
+            vec!["3. Loading...", ""]
+        );
+    }
+
+    #[test]
+    fn test_custom_format() {
+        let now = DateTime::from_timestamp(1_000_000_000, 0).unwrap();
+        let format = RowFormat::parse("{points} {title}[ by {author}]").unwrap();
+        assert_eq!(format.height(), 1);
+        assert_eq!(
+            format.render(1, &story(), now),
+            vec!["111 My YC app: Dropbox by dhouston"]
+        );
+
+        assert!(RowFormat::parse("{karma}").is_err());
+        assert!(RowFormat::parse("[{title}").is_err());
+        assert!(RowFormat::parse("{title}]").is_err());
+    }
+
+    #[test]
+    fn test_relative_age() {
+        let now = DateTime::from_timestamp(1_000_000_000, 0).unwrap();
+        let ago = |seconds| relative_age(now - chrono::Duration::seconds(seconds), now);
+        assert_eq!(ago(30), "just now");
+        assert_eq!(ago(5 * 60), "5m ago");
+        assert_eq!(ago(3 * 60 * 60), "3h ago");
+        assert_eq!(ago(2 * 24 * 60 * 60), "2d ago");
+        assert_eq!(ago(400 * 24 * 60 * 60), "1y ago");
+    }
+}
@@ -0,0 +1,383 @@
+use std::{
+    collections::HashSet,
+    io::{Error, ErrorKind, Result},
+};
+
+use hackernews::HN_ITEM_URL;
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use uuid::Uuid;
+
+use crate::storages::save_data::SaveData;
+
+/// A story saved for later.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Bookmark {
+    pub id: usize,
+    pub title: String,
+    pub url: Option<String>,
+    /// Unix seconds.
+    pub saved_at: i64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct BookmarksData {
+    /// Newest first.
+    bookmarks: Vec<Bookmark>,
+}
+
+/// Stories saved for later, newest first.
+#[derive(Debug)]
+pub struct Bookmarks {
+    storage: SaveData<BookmarksData>,
+}
+
+impl Bookmarks {
+    pub fn new() -> Result<Self> {
+        #[cfg(test)]
+        let file_name = format!("bookmarks_test_{}", Uuid::new_v4());
+        #[cfg(not(test))]
+        let file_name = "bookmarks".to_string();
+        let storage = SaveData::new(file_name, BookmarksData::default())?;
+        Ok(Bookmarks { storage })
+    }
+
+    /// Bookmarks that are forgotten on exit.
+    pub fn in_memory() -> Self {
+        Bookmarks {
+            storage: SaveData::in_memory(BookmarksData::default()),
+        }
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Bookmark> {
+        self.storage
+            .data
+            .bookmarks
+            .iter()
+            .find(|bookmark| bookmark.id == id)
+    }
+
+    /// Saved stories with `tag`, or all of them.
+    pub fn list(&self, tag: Option<&str>) -> Vec<&Bookmark> {
+        self.storage
+            .data
+            .bookmarks
+            .iter()
+            .filter(|bookmark| tag.is_none_or(|tag| bookmark.tags.iter().any(|t| t == tag)))
+            .collect()
+    }
+
+    /// Every tag in use, sorted.
+    pub fn tags(&self) -> Vec<&str> {
+        let mut tags = self
+            .storage
+            .data
+            .bookmarks
+            .iter()
+            .flat_map(|bookmark| bookmark.tags.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+        tags.sort_unstable();
+        tags.dedup();
+        tags
+    }
+
+    /// Saves `bookmark`, or removes it if its story is already saved.
+    /// Returns whether it is saved now.
+    pub fn toggle(&mut self, bookmark: Bookmark) -> Result<bool> {
+        self.storage.load()?;
+        let bookmarks = &mut self.storage.data.bookmarks;
+        let saved = match bookmarks.iter().position(|saved| saved.id == bookmark.id) {
+            Some(position) => {
+                bookmarks.remove(position);
+                false
+            }
+            None => {
+                bookmarks.insert(0, bookmark);
+                true
+            }
+        };
+        self.storage.save()?;
+        Ok(saved)
+    }
+
+    /// Replaces the tags of a saved story.
+    pub fn set_tags(&mut self, id: usize, tags: Vec<String>) -> Result<()> {
+        self.storage.load()?;
+        let Some(bookmark) = self
+            .storage
+            .data
+            .bookmarks
+            .iter_mut()
+            .find(|bookmark| bookmark.id == id)
+        else {
+            return Ok(());
+        };
+        bookmark.tags = tags;
+        self.storage.save()
+    }
+
+    /// Adds the stories that are not saved yet. Returns how many were added.
+    pub fn import(&mut self, bookmarks: Vec<Bookmark>) -> Result<usize> {
+        self.storage.load()?;
+        let saved = &mut self.storage.data.bookmarks;
+        let before = saved.len();
+        for bookmark in bookmarks {
+            if !saved.iter().any(|saved| saved.id == bookmark.id) {
+                saved.push(bookmark);
+            }
+        }
+        saved.sort_by_key(|bookmark| std::cmp::Reverse(bookmark.saved_at));
+        let added = saved.len() - before;
+        self.storage.save()?;
+        Ok(added)
+    }
+
+    /// All saved stories, newest first.
+    pub fn all(&self) -> &[Bookmark] {
+        &self.storage.data.bookmarks
+    }
+
+    #[cfg(test)]
+    pub fn remove(&mut self) -> Result<()> {
+        self.storage.remove()
+    }
+}
+
+/// Splits what was typed for a story's tags, e.g. `rust, databases`, in the
+/// order typed and without repeats.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    input
+        .split([',', ' '])
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty() && seen.insert(*tag))
+        .map(str::to_string)
+        .collect()
+}
+
+pub fn to_json(bookmarks: &[Bookmark]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(bookmarks)?)
+}
+
+pub fn from_json(json: &str) -> Result<Vec<Bookmark>> {
+    Ok(serde_json::from_str(json)?)
+}
+
+/// The bookmark file format browsers and bookmarking services import. The
+/// story id goes in an extra `HN_ID` attribute, which they ignore.
+pub fn to_netscape_html(bookmarks: &[Bookmark]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+         <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+         <TITLE>Bookmarks</TITLE>\n\
+         <H1>Bookmarks</H1>\n\
+         <DL><p>\n",
+    );
+    for bookmark in bookmarks {
+        let href = bookmark
+            .url
+            .clone()
+            .unwrap_or_else(|| format!("{}{}", HN_ITEM_URL, bookmark.id));
+        html.push_str(&format!(
+            "    <DT><A HREF=\"{}\" ADD_DATE=\"{}\" HN_ID=\"{}\"",
+            escape(&href),
+            bookmark.saved_at,
+            bookmark.id
+        ));
+        if !bookmark.tags.is_empty() {
+            html.push_str(&format!(" TAGS=\"{}\"", escape(&bookmark.tags.join(","))));
+        }
+        html.push_str(&format!(">{}</A>\n", escape(&bookmark.title)));
+    }
+    html.push_str("</DL><p>\n");
+    html
+}
+
+/// Reads the links of a Netscape bookmark file. Links without an `HN_ID`
+/// that are not Hacker News discussions can't be listed, and are skipped.
+pub fn from_netscape_html(html: &str) -> Result<Vec<Bookmark>> {
+    if !html.contains("NETSCAPE-Bookmark-file-1") {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Not a Netscape bookmark file",
+        ));
+    }
+
+    // Lowercasing ASCII keeps byte offsets, so both strings share them.
+    let lower = html.to_ascii_lowercase();
+    let mut bookmarks = Vec::new();
+    let mut rest = 0;
+    while let Some(start) = lower[rest..].find("<a ").map(|i| rest + i) {
+        let Some(tag_end) = lower[start..].find('>').map(|i| start + i) else {
+            break;
+        };
+        let text_end = lower[tag_end..]
+            .find("</a>")
+            .map_or(lower.len(), |i| tag_end + i);
+        rest = text_end;
+
+        let attributes = parse_attributes(&html[start + 3..tag_end]);
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        let href = attribute("href").unwrap_or_default();
+        let id = attribute("hn_id")
+            .or_else(|| href.strip_prefix(HN_ITEM_URL))
+            .and_then(|id| id.parse().ok());
+        let Some(id) = id else {
+            continue;
+        };
+        let is_discussion = href.starts_with(HN_ITEM_URL);
+        bookmarks.push(Bookmark {
+            id,
+            title: unescape(html[tag_end + 1..text_end].trim()),
+            url: (!href.is_empty() && !is_discussion).then(|| href.to_string()),
+            saved_at: attribute("add_date")
+                .and_then(|date| date.parse().ok())
+                .unwrap_or_default(),
+            tags: parse_tags(attribute("tags").unwrap_or_default()),
+        });
+    }
+    Ok(bookmarks)
+}
+
+/// `name="value"` pairs of a tag, values unescaped.
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = tag.trim_start();
+    while let Some(eq) = rest.find('=') {
+        let name = rest[..eq].trim().to_string();
+        let after = rest[eq + 1..].trim_start();
+        let (value, next) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                (&quoted[..end], quoted.get(end + 1..).unwrap_or_default())
+            }
+            None => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        attributes.push((name, unescape(value)));
+        rest = next.trim_start();
+    }
+    attributes
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(id: usize, saved_at: i64, tags: &[&str]) -> Bookmark {
+        Bookmark {
+            id,
+            title: format!("Story {}", id),
+            url: Some(format!("https://example.com/{}?a=1&b=2", id)),
+            saved_at,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_toggle_and_filter() {
+        let mut bookmarks = Bookmarks::new().unwrap();
+        assert!(bookmarks.toggle(bookmark(1, 100, &["rust"])).unwrap());
+        assert!(bookmarks.toggle(bookmark(2, 200, &[])).unwrap());
+        bookmarks
+            .set_tags(2, parse_tags("rust, databases"))
+            .unwrap();
+
+        let ids = |list: Vec<&Bookmark>| list.iter().map(|b| b.id).collect::<Vec<_>>();
+        assert_eq!(ids(bookmarks.list(None)), vec![2, 1]);
+        assert_eq!(ids(bookmarks.list(Some("databases"))), vec![2]);
+        assert_eq!(bookmarks.tags(), vec!["databases", "rust"]);
+
+        assert!(!bookmarks.toggle(bookmark(1, 300, &[])).unwrap());
+        assert!(!bookmarks.contains(1));
+
+        bookmarks.remove().unwrap();
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(parse_tags("rust, db, rust"), vec!["rust", "db"]);
+        assert_eq!(parse_tags(" ,web  tui,"), vec!["web", "tui"]);
+    }
+
+    #[test]
+    fn test_netscape_html_round_trip() {
+        let ask = Bookmark {
+            url: None,
+            title: "Ask HN: \"Tabs\" <or> spaces?".to_string(),
+            ..bookmark(3, 300, &[])
+        };
+        let saved = vec![ask, bookmark(2, 200, &["rust", "db"])];
+
+        let html = to_netscape_html(&saved);
+        assert!(html.contains("HREF=\"https://news.ycombinator.com/item?id=3\""));
+        assert!(html.contains("HREF=\"https://example.com/2?a=1&amp;b=2\""));
+        assert_eq!(from_netscape_html(&html).unwrap(), saved);
+    }
+
+    #[test]
+    fn test_netscape_html_from_browser() {
+        let html = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3>Reading</H3>
+    <DL><p>
+        <DT><a href="https://news.ycombinator.com/item?id=8863" add_date="1175714200">My YC app: Dropbox</a>
+        <DT><a href="https://www.rust-lang.org/">Rust</a>
+    </DL><p>
+</DL><p>"#;
+        assert_eq!(
+            from_netscape_html(html).unwrap(),
+            vec![Bookmark {
+                id: 8863,
+                title: "My YC app: Dropbox".to_string(),
+                url: None,
+                saved_at: 1175714200,
+                tags: Vec::new(),
+            }]
+        );
+        assert!(from_netscape_html("[]").is_err());
+    }
+
+    #[test]
+    fn test_import_skips_saved_stories() {
+        let mut bookmarks = Bookmarks::new().unwrap();
+        bookmarks.toggle(bookmark(1, 100, &[])).unwrap();
+
+        let json = to_json(&[bookmark(1, 500, &["old"]), bookmark(2, 200, &[])]).unwrap();
+        assert_eq!(bookmarks.import(from_json(&json).unwrap()).unwrap(), 1);
+        assert_eq!(
+            bookmarks.all(),
+            &[bookmark(2, 200, &[]), bookmark(1, 100, &[])]
+        );
+
+        bookmarks.remove().unwrap();
+    }
+}
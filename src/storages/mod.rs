@@ -1,4 +1,8 @@
+pub mod bookmarks;
 mod read_history;
 mod save_data;
+mod visits;
 
-pub use read_history::ReadHistory;
+pub use bookmarks::{Bookmark, Bookmarks};
+pub use read_history::{HistoryLimits, ReadHistory};
+pub use visits::{Visit, Visits};
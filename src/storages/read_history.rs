@@ -3,6 +3,7 @@ use std::{
     io::Result,
 };
 
+use chrono::{Duration, Utc};
 use hackernews::StoryType;
 use serde::{Deserialize, Serialize};
 #[cfg(test)]
@@ -10,52 +11,170 @@ use uuid::Uuid;
 
 use crate::storages::save_data::SaveData;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// A story opened from the list.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Read {
+    pub id: usize,
+    /// Unix seconds.
+    pub at: i64,
+    /// To search the history by. Unknown for reads from before it was kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// How much read history is kept. The oldest reads go first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryLimits {
+    pub max_entries: usize,
+    /// Reads older than this are forgotten. `None` keeps them.
+    pub max_age: Option<Duration>,
+}
+
+impl Default for HistoryLimits {
+    fn default() -> Self {
+        HistoryLimits {
+            max_entries: 1000,
+            max_age: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(from = "ReadHistoryFile")]
 struct ReadHistoryData {
-    #[serde(flatten)]
-    pub data: HashMap<StoryType, BTreeSet<usize>>,
+    /// Oldest first, each story once.
+    reads: Vec<Read>,
+}
+
+/// What the history file may hold.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ReadHistoryFile {
+    Timeline {
+        reads: Vec<Read>,
+    },
+    /// The ids read under each topic, from before reads were timed.
+    PerTopic(HashMap<StoryType, BTreeSet<usize>>),
+}
+
+impl From<ReadHistoryFile> for ReadHistoryData {
+    fn from(file: ReadHistoryFile) -> Self {
+        match file {
+            ReadHistoryFile::Timeline { reads } => ReadHistoryData { reads },
+            ReadHistoryFile::PerTopic(topics) => {
+                // When they were read is lost. Ids grow over time, so lower
+                // ones count as read earlier, all as of the migration.
+                let ids = topics.into_values().flatten().collect::<BTreeSet<_>>();
+                let at = Utc::now().timestamp();
+                ReadHistoryData {
+                    reads: ids
+                        .into_iter()
+                        .map(|id| Read {
+                            id,
+                            at,
+                            title: None,
+                        })
+                        .collect(),
+                }
+            }
+        }
+    }
 }
 
+/// Stories read, whatever topic they were read under.
 #[derive(Debug)]
 pub struct ReadHistory {
     storage: SaveData<ReadHistoryData>,
-    max: usize,
+    limits: HistoryLimits,
 }
 
 impl ReadHistory {
-    pub fn new(max: usize) -> Self {
+    pub fn new(limits: HistoryLimits) -> Result<Self> {
         #[cfg(test)]
         let file_name = format!("read_history_test_{}", Uuid::new_v4());
         #[cfg(not(test))]
         let file_name = "read_history".to_string();
-        let storage = SaveData::new(
-            file_name,
-            ReadHistoryData {
-                data: HashMap::new(),
-            },
-        );
-        ReadHistory { storage, max }
+        let storage = SaveData::new(file_name, ReadHistoryData::default())?;
+        Ok(ReadHistory { storage, limits })
     }
 
-    pub fn add_read_item(&mut self, story_type: StoryType, item_id: usize) -> Result<()> {
-        self.storage.load()?;
-        let history = &mut self.storage.data.data;
-        let items = history.entry(story_type).or_default();
-        if items.len() >= self.max {
-            if let Some(oldest) = items.iter().next().cloned() {
-                items.remove(&oldest);
-            }
+    /// A history that is forgotten on exit.
+    pub fn in_memory(limits: HistoryLimits) -> Self {
+        let storage = SaveData::in_memory(ReadHistoryData::default());
+        ReadHistory { storage, limits }
+    }
+
+    /// Applies new limits, forgetting what no longer fits as of `now`.
+    pub fn set_limits(&mut self, limits: HistoryLimits, now: i64) -> Result<()> {
+        self.limits = limits;
+        if self.prune(now) {
+            self.storage.save()?;
         }
-        items.insert(item_id);
+        Ok(())
+    }
+
+    /// Records that `item_id` was read at `at`, which makes it the most
+    /// recent read even if it was read before.
+    pub fn add_read_item(&mut self, item_id: usize, title: Option<String>, at: i64) -> Result<()> {
+        self.storage.load()?;
+        let reads = &mut self.storage.data.reads;
+        // A story read again keeps its title if it is not known this time.
+        let title = title.or_else(|| {
+            let previous = reads.iter().find(|read| read.id == item_id)?;
+            previous.title.clone()
+        });
+        reads.retain(|read| read.id != item_id);
+        reads.push(Read {
+            id: item_id,
+            at,
+            title,
+        });
+        self.prune(at);
+        self.storage.save()
+    }
+
+    /// Takes `item_id` out of the history, as if it was never read.
+    pub fn forget(&mut self, item_id: usize) -> Result<()> {
+        self.storage.load()?;
+        self.storage.data.reads.retain(|read| read.id != item_id);
+        self.storage.save()
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.storage.data.reads.clear();
         self.storage.save()
     }
 
-    pub fn id_is_readed(&self, story_type: StoryType, item_id: usize) -> bool {
+    /// Every read, newest first.
+    pub fn reads(&self) -> impl Iterator<Item = &Read> {
+        self.storage.data.reads.iter().rev()
+    }
+
+    /// Drops the reads past the limits. Returns whether any were.
+    fn prune(&mut self, now: i64) -> bool {
+        let reads = &mut self.storage.data.reads;
+        let before = reads.len();
+        if let Some(max_age) = self.limits.max_age {
+            let oldest = now - max_age.num_seconds();
+            reads.retain(|read| read.at >= oldest);
+        }
+        let excess = reads.len().saturating_sub(self.limits.max_entries);
+        reads.drain(..excess);
+        reads.len() != before
+    }
+
+    pub fn id_is_readed(&self, item_id: usize) -> bool {
+        self.read_at(item_id).is_some()
+    }
+
+    /// When `item_id` was last read.
+    pub fn read_at(&self, item_id: usize) -> Option<i64> {
         self.storage
             .data
-            .data
-            .get(&story_type)
-            .is_some_and(|ids| ids.contains(&item_id))
+            .reads
+            .iter()
+            .find(|read| read.id == item_id)
+            .map(|read| read.at)
     }
 
     #[cfg(test)]
@@ -68,36 +187,99 @@ impl ReadHistory {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use hackernews::StoryType;
+
+    fn limits(max_entries: usize) -> HistoryLimits {
+        HistoryLimits {
+            max_entries,
+            max_age: None,
+        }
+    }
 
     #[test]
     fn test_add_read_item() {
-        let mut history = ReadHistory::new(100);
-        history.add_read_item(StoryType::Show, 42).unwrap();
-        assert!(history.id_is_readed(StoryType::Show, 42));
+        let mut history = ReadHistory::new(limits(100)).unwrap();
+        history.add_read_item(42, None, 1_000).unwrap();
+        assert!(history.id_is_readed(42));
+        assert_eq!(history.read_at(42), Some(1_000));
 
         history.remove().unwrap();
     }
 
     #[test]
     fn test_id_is_not_readed() {
-        let mut history = ReadHistory::new(100);
-        assert!(!history.id_is_readed(StoryType::Show, 100));
+        let mut history = ReadHistory::new(limits(100)).unwrap();
+        assert!(!history.id_is_readed(100));
 
         history.remove().unwrap();
     }
 
     #[test]
     fn test_add_read_item_exceed_max() {
-        let mut history = ReadHistory::new(2);
-        history.add_read_item(StoryType::Show, 1).unwrap();
-        history.add_read_item(StoryType::Show, 2).unwrap();
-        history.add_read_item(StoryType::Show, 3).unwrap();
+        let mut history = ReadHistory::new(limits(2)).unwrap();
+        history.add_read_item(3, None, 1_000).unwrap();
+        history.add_read_item(1, None, 2_000).unwrap();
+        // Reading it again makes it the most recent.
+        history.add_read_item(3, None, 3_000).unwrap();
+        history.add_read_item(2, None, 4_000).unwrap();
+
+        assert!(!history.id_is_readed(1));
+        assert!(history.id_is_readed(2));
+        assert!(history.id_is_readed(3));
+
+        history.remove().unwrap();
+    }
+
+    #[test]
+    fn test_forget_and_clear() {
+        let mut history = ReadHistory::new(limits(100)).unwrap();
+        history
+            .add_read_item(1, Some("First".to_string()), 1_000)
+            .unwrap();
+        history.add_read_item(2, None, 2_000).unwrap();
+        history.add_read_item(3, None, 3_000).unwrap();
+        let ids = |history: &ReadHistory| history.reads().map(|read| read.id).collect::<Vec<_>>();
+        assert_eq!(ids(&history), vec![3, 2, 1]);
+
+        history.add_read_item(1, None, 4_000).unwrap();
+        assert_eq!(
+            history.reads().next().unwrap().title.as_deref(),
+            Some("First")
+        );
+        history.forget(2).unwrap();
+        assert_eq!(ids(&history), vec![1, 3]);
 
-        assert!(!history.id_is_readed(StoryType::Show, 1));
-        assert!(history.id_is_readed(StoryType::Show, 2));
-        assert!(history.id_is_readed(StoryType::Show, 3));
+        history.clear().unwrap();
+        assert_eq!(ids(&history), Vec::<usize>::new());
 
         history.remove().unwrap();
     }
+
+    #[test]
+    fn test_max_age() {
+        let mut history = ReadHistory::new(limits(100)).unwrap();
+        history.add_read_item(1, None, 0).unwrap();
+        history.add_read_item(2, None, 2 * 24 * 60 * 60).unwrap();
+
+        let limits = HistoryLimits {
+            max_age: Some(Duration::days(1)),
+            ..limits(100)
+        };
+        history.set_limits(limits, 2 * 24 * 60 * 60).unwrap();
+        assert!(!history.id_is_readed(1));
+        assert!(history.id_is_readed(2));
+
+        history.remove().unwrap();
+    }
+
+    #[test]
+    fn test_migrate_per_topic_history() {
+        let data: ReadHistoryData =
+            serde_json::from_str(r#"{"Show": [7, 3], "Top": [5, 3]}"#).unwrap();
+        let ids = data.reads.iter().map(|read| read.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![3, 5, 7]);
+
+        let saved = serde_json::to_string(&data).unwrap();
+        let data: ReadHistoryData = serde_json::from_str(&saved).unwrap();
+        assert_eq!(data.reads.len(), 3);
+    }
 }
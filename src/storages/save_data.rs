@@ -1,9 +1,12 @@
+use std::io::ErrorKind;
+
 use directories::BaseDirs;
 use serde::{Serialize, de::DeserializeOwned};
 
 #[derive(Debug)]
 pub struct SaveData<T> {
-    path: String,
+    /// `None` keeps the data in memory only.
+    path: Option<String>,
     pub data: T,
 }
 
@@ -11,47 +14,58 @@ impl<T> SaveData<T>
 where
     T: Serialize + DeserializeOwned + Clone,
 {
-    pub fn new(file_name: String, init_data: T) -> Self {
-        let base_dirs = BaseDirs::new().unwrap();
+    pub fn new(file_name: String, init_data: T) -> std::io::Result<Self> {
+        let base_dirs = BaseDirs::new()
+            .ok_or_else(|| std::io::Error::new(ErrorKind::NotFound, "No home directory"))?;
         let cache_dir = base_dirs.cache_dir();
         let file_path = cache_dir.join(format!("./hn-rs/{}.json", file_name));
-        let path = file_path.display().to_string();
+        let mut save_data = SaveData {
+            path: Some(file_path.display().to_string()),
+            data: init_data,
+        };
         if !file_path.exists() {
-            std::fs::create_dir_all(file_path.parent().unwrap())
-                .expect("Failed to create directory");
-            std::fs::File::create(&file_path).expect("Failed to create file");
-            SaveData {
-                path,
-                data: init_data,
+            if let Some(parent) = file_path.parent() {
+                std::fs::create_dir_all(parent)?;
             }
+            std::fs::File::create(&file_path)?;
         } else {
-            let mut save_data = SaveData {
-                path,
-                data: init_data,
-            };
-            save_data.load().expect("Failed to load data");
-            save_data
+            save_data.load()?;
+        }
+        Ok(save_data)
+    }
+
+    /// Data that is never written to disk, for when the file can't be used.
+    pub fn in_memory(init_data: T) -> Self {
+        SaveData {
+            path: None,
+            data: init_data,
         }
     }
 
     pub fn save(&self) -> std::io::Result<()> {
-        let file = std::fs::File::create(&self.path)?;
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let file = std::fs::File::create(path)?;
         serde_json::to_writer(file, &self.data)?;
         Ok(())
     }
 
     pub fn load(&mut self) -> std::io::Result<()> {
-        let file = std::fs::File::open(&self.path)?;
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let file = std::fs::File::open(path)?;
         self.data = serde_json::from_reader(file).unwrap_or_else(|_| self.data.clone());
         Ok(())
     }
 
     #[cfg(test)]
     pub fn remove(&mut self) -> std::io::Result<()> {
-        if std::fs::remove_file(&self.path).is_err() {
-            eprintln!("Failed to remove file: {}", self.path);
+        match &self.path {
+            Some(path) => std::fs::remove_file(path),
+            None => Ok(()),
         }
-        Ok(())
     }
 }
 
@@ -72,7 +86,7 @@ mod tests {
         let data = TestData {
             value: "Hello, World!".to_string(),
         };
-        let mut save_data = SaveData::new("test_data".to_string(), data.clone());
+        let mut save_data = SaveData::new("test_data".to_string(), data.clone())?;
 
         save_data.save()?;
 
@@ -81,7 +95,7 @@ mod tests {
             TestData {
                 value: "".to_string(),
             },
-        );
+        )?;
         loaded_data.load()?;
 
         assert_eq!(loaded_data.data, data);
@@ -0,0 +1,187 @@
+use std::{collections::HashMap, io::Result};
+
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use uuid::Uuid;
+
+use crate::storages::save_data::SaveData;
+
+/// The last time a story's comments were read.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Visit {
+    /// Unix seconds.
+    pub at: i64,
+    /// The story's comment count back then.
+    pub comments: usize,
+    /// Highest comment id loaded. Ids only grow, so any higher one is new.
+    pub max_seen: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct VisitsData {
+    visits: HashMap<usize, Visit>,
+}
+
+/// Comment threads read before, by story id.
+#[derive(Debug)]
+pub struct Visits {
+    storage: SaveData<VisitsData>,
+    max: usize,
+    /// Highest comment id seen per story since the visits were last saved.
+    unsaved: HashMap<usize, usize>,
+}
+
+impl Visits {
+    pub fn new(max: usize) -> Result<Self> {
+        #[cfg(test)]
+        let file_name = format!("visits_test_{}", Uuid::new_v4());
+        #[cfg(not(test))]
+        let file_name = "visits".to_string();
+        let storage = SaveData::new(file_name, VisitsData::default())?;
+        Ok(Visits {
+            storage,
+            max,
+            unsaved: HashMap::new(),
+        })
+    }
+
+    /// Visits that are forgotten on exit.
+    pub fn in_memory(max: usize) -> Self {
+        let storage = SaveData::in_memory(VisitsData::default());
+        Visits {
+            storage,
+            max,
+            unsaved: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, story_id: usize) -> Option<Visit> {
+        self.storage.data.visits.get(&story_id).copied()
+    }
+
+    /// Comments added to a story since its last visit, given its current
+    /// comment count. Zero for stories never visited.
+    pub fn new_comments(&self, story_id: usize, comments: usize) -> usize {
+        self.get(story_id)
+            .map_or(0, |visit| comments.saturating_sub(visit.comments))
+    }
+
+    /// Starts a visit to a story with `comments` comments, returning the
+    /// previous one.
+    pub fn visit(&mut self, story_id: usize, comments: usize, at: i64) -> Result<Option<Visit>> {
+        self.storage.load()?;
+        self.apply_unsaved();
+        let visits = &mut self.storage.data.visits;
+        let previous = visits.get(&story_id).copied();
+        if previous.is_none()
+            && visits.len() >= self.max
+            && let Some(oldest) = visits
+                .iter()
+                .min_by_key(|(_, visit)| visit.at)
+                .map(|(&id, _)| id)
+        {
+            visits.remove(&oldest);
+        }
+        visits.insert(
+            story_id,
+            Visit {
+                at,
+                comments,
+                max_seen: previous.map_or(0, |visit| visit.max_seen),
+            },
+        );
+        self.storage.save()?;
+        Ok(previous)
+    }
+
+    /// Notes that comments up to `max_id` were loaded during the visit. Kept
+    /// in memory until [`Visits::save_seen`].
+    pub fn see(&mut self, story_id: usize, max_id: usize) {
+        if let Some(visit) = self.storage.data.visits.get_mut(&story_id)
+            && visit.max_seen < max_id
+        {
+            visit.max_seen = max_id;
+            self.unsaved.insert(story_id, max_id);
+        }
+    }
+
+    /// Writes the comments seen since the last save.
+    pub fn save_seen(&mut self) -> Result<()> {
+        if self.unsaved.is_empty() {
+            return Ok(());
+        }
+        self.storage.load()?;
+        self.apply_unsaved();
+        self.storage.save()
+    }
+
+    /// Merges the comments seen into the visits, which may have just been
+    /// loaded from disk.
+    fn apply_unsaved(&mut self) {
+        for (story_id, max_id) in self.unsaved.drain() {
+            if let Some(visit) = self.storage.data.visits.get_mut(&story_id) {
+                visit.max_seen = visit.max_seen.max(max_id);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub fn remove(&mut self) -> Result<()> {
+        self.storage.remove()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visit() {
+        let mut visits = Visits::new(100).unwrap();
+        assert_eq!(visits.visit(1, 10, 1_000).unwrap(), None);
+        assert_eq!(visits.new_comments(1, 14), 4);
+        assert_eq!(visits.new_comments(2, 14), 0);
+
+        visits.see(1, 500);
+        visits.see(1, 400);
+        let previous = visits.visit(1, 14, 2_000).unwrap().unwrap();
+        assert_eq!(previous.max_seen, 500);
+        assert_eq!(previous.comments, 10);
+        // Until more are loaded, the new visit has seen the same comments.
+        assert_eq!(visits.get(1).unwrap().max_seen, 500);
+        assert_eq!(visits.new_comments(1, 14), 0);
+
+        visits.remove().unwrap();
+    }
+
+    #[test]
+    fn test_save_seen() {
+        let mut visits = Visits::new(100).unwrap();
+        visits.visit(1, 10, 1_000).unwrap();
+        visits.see(1, 500);
+
+        // Nothing is written until the seen comments are saved.
+        visits.storage.load().unwrap();
+        assert_eq!(visits.get(1).unwrap().max_seen, 0);
+
+        visits.save_seen().unwrap();
+        visits.storage.load().unwrap();
+        assert_eq!(visits.get(1).unwrap().max_seen, 500);
+
+        visits.remove().unwrap();
+    }
+
+    #[test]
+    fn test_visit_exceed_max() {
+        let mut visits = Visits::new(2).unwrap();
+        visits.visit(1, 0, 3_000).unwrap();
+        visits.visit(2, 0, 1_000).unwrap();
+        visits.visit(3, 0, 2_000).unwrap();
+
+        assert!(visits.get(1).is_some());
+        assert!(visits.get(2).is_none());
+        assert!(visits.get(3).is_some());
+
+        visits.remove().unwrap();
+    }
+}
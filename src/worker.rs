@@ -0,0 +1,635 @@
+use std::time::Duration;
+
+use futures::{
+    StreamExt,
+    future::join_all,
+    stream::{self, FuturesUnordered},
+};
+use hackernews::{
+    HackerNewsClient, HnError, StoryType,
+    get_items::TreeOptions,
+    search::{SearchClient, SearchQuery, Tag},
+    subscribe::Event,
+};
+use tokio::{
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    task::{JoinHandle, JoinSet},
+};
+
+use crate::{AppAction, AppData, AppError};
+
+/// How often the watcher asks the API what changed.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A background task that is aborted when it is replaced or dropped.
+#[derive(Default)]
+struct Task(Option<JoinHandle<()>>);
+
+impl Task {
+    fn replace(&mut self, handle: JoinHandle<()>) {
+        self.abort();
+        self.0 = Some(handle);
+    }
+
+    fn abort(&mut self) {
+        if let Some(handle) = self.0.take() {
+            handle.abort();
+        }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        self.abort();
+    }
+}
+
+/// Runs the requests the UI asks for and sends the results back. Each kind of
+/// request has its own slot: a new one cancels the one it supersedes, and
+/// dropping the worker cancels everything.
+pub struct Worker {
+    client: HackerNewsClient,
+    search: SearchClient,
+    tx_data: UnboundedSender<AppData>,
+    tx_error: UnboundedSender<AppError>,
+    story: Task,
+    pages: JoinSet<()>,
+    comments: Task,
+    item: Task,
+    poll: Task,
+    user: Task,
+    submitted: JoinSet<()>,
+    watch: Task,
+    watched: Option<usize>,
+    background: JoinSet<()>,
+}
+
+impl Worker {
+    pub fn new(
+        client: HackerNewsClient,
+        search: SearchClient,
+        tx_data: UnboundedSender<AppData>,
+        tx_error: UnboundedSender<AppError>,
+    ) -> Self {
+        Worker {
+            client,
+            search,
+            tx_data,
+            tx_error,
+            story: Task::default(),
+            pages: JoinSet::new(),
+            comments: Task::default(),
+            item: Task::default(),
+            poll: Task::default(),
+            user: Task::default(),
+            submitted: JoinSet::new(),
+            watch: Task::default(),
+            watched: None,
+            background: JoinSet::new(),
+        }
+    }
+
+    /// Handles actions as they arrive, until the UI hangs up.
+    pub async fn run(mut self, mut rx_action: UnboundedReceiver<AppAction>) {
+        self.background.spawn(watch_updates(
+            self.client.clone(),
+            self.tx_data.clone(),
+            self.tx_error.clone(),
+        ));
+        self.background.spawn(watch_stories(
+            self.client.clone(),
+            StoryType::Top,
+            self.tx_data.clone(),
+        ));
+
+        while let Some(action) = rx_action.recv().await {
+            self.handle(action);
+        }
+    }
+
+    fn handle(&mut self, mut action: AppAction) {
+        let mut refresh = false;
+        let mut reload = false;
+        while let AppAction::Refresh(_) | AppAction::Reload(_) = action {
+            refresh = true;
+            action = match action {
+                AppAction::Reload(inner) => {
+                    reload = true;
+                    *inner
+                }
+                AppAction::Refresh(inner) => *inner,
+                action => action,
+            };
+        }
+
+        let tx_data = self.tx_data.clone();
+        let tx_error = self.tx_error.clone();
+        let client = self.client.clone();
+        match action {
+            AppAction::Story(topic) => {
+                self.pages.abort_all();
+                self.story.replace(tokio::spawn(async move {
+                    if reload {
+                        client.invalidate_stories(topic).await;
+                    }
+                    // Show the last known feed from the disk cache while
+                    // the fresh one loads.
+                    if !refresh && let Some(list) = client.cached_stories(topic).await {
+                        let cached = join_all(list.iter().map(|&id| client.cached_item(id)))
+                            .await
+                            .into_iter()
+                            .flatten()
+                            .collect::<Vec<_>>();
+                        let _ = tx_data.send(AppData::Story(Some(list)));
+                        if !cached.is_empty() {
+                            let _ = tx_data.send(AppData::StoryItems(cached));
+                        }
+                    }
+
+                    // Only the ids: the list asks for the rows it shows.
+                    match client.get_stories(topic).await {
+                        Ok(list) => {
+                            let _ = tx_data.send(AppData::Story(Some(list)));
+                        }
+                        Err(e) => {
+                            let error = AppError::new(format!("Could not load {}: {}", topic, e))
+                                .retry(AppAction::Story(topic));
+                            let _ = tx_error.send(error);
+                        }
+                    }
+                }));
+            }
+
+            AppAction::StoryItems(ids) => {
+                while self.pages.try_join_next().is_some() {}
+                self.pages.spawn(async move {
+                    if reload {
+                        join_all(ids.iter().map(|&id| client.invalidate_item(id))).await;
+                    }
+                    // Each row is sent as soon as it arrives, so one slow
+                    // item does not hold back the others.
+                    let mut pending = ids
+                        .iter()
+                        .map(|&id| {
+                            let client = &client;
+                            async move { (id, client.get_item(id).await) }
+                        })
+                        .collect::<FuturesUnordered<_>>();
+                    let mut failed = Vec::new();
+                    let mut last_error = None;
+                    while let Some((id, res)) = pending.next().await {
+                        match res {
+                            Ok(item) => {
+                                let _ = tx_data.send(AppData::StoryItems(vec![item]));
+                            }
+                            Err(e) if report(&e) => {
+                                failed.push(id);
+                                last_error = Some(e);
+                            }
+                            Err(_) => {}
+                        }
+                    }
+                    if let Some(e) = last_error {
+                        let error = AppError::new(format!(
+                            "Could not load {} stories: {}",
+                            failed.len(),
+                            e
+                        ))
+                        .retry(AppAction::StoryItems(failed));
+                        let _ = tx_error.send(error);
+                    }
+                });
+            }
+
+            AppAction::Items(items) => {
+                self.comments.abort();
+                if items.is_empty() {
+                    return;
+                }
+
+                self.comments.replace(tokio::spawn(async move {
+                    if reload {
+                        join_all(items.iter().map(|&id| client.invalidate_item_tree(id))).await;
+                    }
+                    // Threads load side by side and each is shown as soon as
+                    // it is complete, so a long one does not hold back the
+                    // others. The panel keeps them in the order of `items`.
+                    let options = TreeOptions::default();
+                    let mut threads = stream::iter(items)
+                        .map(|id| {
+                            let client = client.clone();
+                            async move { (id, client.get_item_tree(id, options).await) }
+                        })
+                        .buffer_unordered(options.max_concurrent_requests);
+                    let mut failed = Vec::new();
+                    let mut last_error = None;
+                    while let Some((id, res)) = threads.next().await {
+                        match res {
+                            Ok(item) => {
+                                let _ = tx_data.send(AppData::Comment(Some(Box::new(item))));
+                            }
+                            Err(e) if report(&e) => {
+                                failed.push(id);
+                                last_error = Some(e);
+                            }
+                            Err(_) => {}
+                        }
+                    }
+                    if let Some(e) = last_error {
+                        let error = AppError::new(format!(
+                            "Could not load {} comment threads: {}",
+                            failed.len(),
+                            e
+                        ))
+                        .retry(AppAction::Items(failed));
+                        let _ = tx_error.send(error);
+                    }
+                    let _ = tx_data.send(AppData::CommentsLoaded);
+                }));
+            }
+
+            AppAction::Item(item_id) => {
+                self.item.replace(tokio::spawn(async move {
+                    if reload {
+                        client.invalidate_item(item_id).await;
+                    }
+                    match client.get_item(item_id).await {
+                        Ok(item) => {
+                            let _ = tx_data.send(AppData::Item(Box::new(item)));
+                        }
+                        Err(e) => {
+                            let error = AppError::new(format!("Could not reload the story: {}", e))
+                                .retry(AppAction::Item(item_id));
+                            let _ = tx_error.send(error);
+                        }
+                    }
+                }));
+            }
+
+            AppAction::Poll(poll_id) => {
+                self.poll.replace(tokio::spawn(async move {
+                    match client.get_poll(poll_id).await {
+                        Ok(poll) => {
+                            let _ = tx_data.send(AppData::Poll(Box::new(poll)));
+                        }
+                        Err(e) => {
+                            let error = AppError::new(format!("Could not load the poll: {}", e))
+                                .retry(AppAction::Poll(poll_id));
+                            let _ = tx_error.send(error);
+                        }
+                    }
+                }));
+            }
+
+            AppAction::Search { query, page } => {
+                self.pages.abort_all();
+                let search = self.search.clone();
+                self.story.replace(tokio::spawn(async move {
+                    let request = SearchQuery::new(query.clone()).tag(Tag::Story).page(page);
+                    let (ids, has_more): (Vec<usize>, _) = match search.search(&request).await {
+                        Ok(results) => (
+                            results.hits.iter().filter_map(|hit| hit.id()).collect(),
+                            results.has_next_page(),
+                        ),
+                        Err(e) => {
+                            let error = AppError::new(format!("Search failed: {}", e))
+                                .retry(AppAction::Search { query, page });
+                            let _ = tx_error.send(error);
+                            return;
+                        }
+                    };
+                    if reload {
+                        join_all(ids.iter().map(|&id| client.invalidate_item(id))).await;
+                    }
+                    let results = join_all(ids.iter().map(|&id| client.get_item(id))).await;
+                    let mut listed = Vec::new();
+                    let mut items = Vec::new();
+                    let mut failed = Vec::new();
+                    let mut last_error = None;
+                    for (&id, res) in ids.iter().zip(results) {
+                        match res {
+                            Ok(item) => {
+                                listed.push(id);
+                                items.push(item);
+                            }
+                            // Listed anyway, to be filled in by the retry.
+                            Err(e) if report(&e) => {
+                                listed.push(id);
+                                failed.push(id);
+                                last_error = Some(e);
+                            }
+                            Err(_) => {}
+                        }
+                    }
+
+                    let _ = tx_data.send(AppData::Search {
+                        query,
+                        page,
+                        ids: listed,
+                        items,
+                        has_more,
+                    });
+                    if let Some(e) = last_error {
+                        let error = AppError::new(format!(
+                            "Could not load {} search results: {}",
+                            failed.len(),
+                            e
+                        ))
+                        .retry(AppAction::StoryItems(failed));
+                        let _ = tx_error.send(error);
+                    }
+                }));
+            }
+
+            AppAction::User(user_id) => {
+                self.submitted.abort_all();
+                self.user.replace(tokio::spawn(async move {
+                    match client.get_user(&user_id).await {
+                        Ok(user) => {
+                            let _ = tx_data.send(AppData::User(user));
+                        }
+                        Err(e) => {
+                            let error = AppError::new(format!("Could not load {}: {}", user_id, e))
+                                .retry(AppAction::User(user_id));
+                            let _ = tx_error.send(error);
+                        }
+                    }
+                }));
+            }
+
+            AppAction::Submitted { user, ids } => {
+                while self.submitted.try_join_next().is_some() {}
+                self.submitted.spawn(async move {
+                    let results = join_all(ids.iter().map(|&id| client.get_item(id))).await;
+                    let mut items = Vec::new();
+                    let mut failed = Vec::new();
+                    let mut last_error = None;
+                    for (&id, res) in ids.iter().zip(results) {
+                        match res {
+                            Ok(item) => items.push(item),
+                            Err(e) if report(&e) => {
+                                failed.push(id);
+                                last_error = Some(e);
+                            }
+                            Err(_) => {}
+                        }
+                    }
+                    if let Some(e) = last_error {
+                        let error = AppError::new(format!(
+                            "Could not load {} submissions: {}",
+                            failed.len(),
+                            e
+                        ))
+                        .retry(AppAction::Submitted {
+                            user: user.clone(),
+                            ids: failed,
+                        });
+                        let _ = tx_error.send(error);
+                    }
+                    let _ = tx_data.send(AppData::Submitted { user, items });
+                });
+            }
+
+            AppAction::Watch(item_id) => {
+                if self.watched == item_id {
+                    return;
+                }
+                self.watched = item_id;
+                match item_id {
+                    Some(item_id) => self
+                        .watch
+                        .replace(tokio::spawn(watch_item(client, item_id, tx_data))),
+                    None => self.watch.abort(),
+                }
+            }
+
+            AppAction::Refresh(_) | AppAction::Reload(_) => unreachable!("unwrapped above"),
+        }
+    }
+}
+
+/// Whether a failed fetch is worth telling the reader about. Items that do
+/// not exist, e.g. deleted ones, are simply left out.
+fn report(error: &HnError) -> bool {
+    !matches!(error, HnError::NotFound(_))
+}
+
+/// Drops cached copies of everything the API reports as changed and tells the
+/// UI about it, so that it can refetch what is on screen.
+async fn watch_updates(
+    client: HackerNewsClient,
+    tx_data: UnboundedSender<AppData>,
+    tx_error: UnboundedSender<AppError>,
+) {
+    let mut max_item = client.get_max_item().await.ok();
+    let mut interval = tokio::time::interval(UPDATE_INTERVAL);
+    // The first tick fires immediately and nothing is stale yet.
+    interval.tick().await;
+
+    // Reported once until checking works again, not on every tick.
+    let mut failing = false;
+    loop {
+        interval.tick().await;
+
+        let updates = match client.get_updates().await {
+            Ok(updates) => updates,
+            Err(e) => {
+                if !failing {
+                    let error = AppError::new(format!("Could not check for updates: {}", e));
+                    let _ = tx_error.send(error);
+                }
+                failing = true;
+                continue;
+            }
+        };
+        failing = false;
+        client.apply_updates(&updates).await;
+
+        let latest = client.get_max_item().await.ok();
+        let has_new_items = matches!((max_item, latest), (Some(old), Some(new)) if new > old);
+        if latest.is_some() {
+            max_item = latest;
+        }
+        if has_new_items {
+            client.invalidate_stories(StoryType::New).await;
+        }
+
+        let stories = if has_new_items {
+            vec![StoryType::New]
+        } else {
+            Vec::new()
+        };
+        if (!updates.items.is_empty() || !stories.is_empty())
+            && tx_data
+                .send(AppData::Updates {
+                    items: updates.items,
+                    stories,
+                })
+                .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Follows a feed as it changes and asks the UI to reload it.
+async fn watch_stories(
+    client: HackerNewsClient,
+    kind: StoryType,
+    tx_data: UnboundedSender<AppData>,
+) {
+    let mut events = client.subscribe_stories(kind);
+    while let Some(event) = events.next().await {
+        let changed = match event {
+            // Sent on every (re)connect, so often the list is already known.
+            Event::Put { path, data } if path == "/" => {
+                let list = serde_json::from_value(data).ok();
+                list.is_none() || list != client.cached_stories(kind).await
+            }
+            Event::Put { .. } | Event::Patch { .. } => true,
+            Event::KeepAlive | Event::Cancel => false,
+        };
+        if !changed {
+            continue;
+        }
+
+        client.invalidate_stories(kind).await;
+        let updates = AppData::Updates {
+            items: Vec::new(),
+            stories: vec![kind],
+        };
+        if tx_data.send(updates).is_err() {
+            return;
+        }
+    }
+}
+
+/// Follows a single item, so that new replies show up while it is open.
+async fn watch_item(client: HackerNewsClient, item_id: usize, tx_data: UnboundedSender<AppData>) {
+    let mut events = client.subscribe_item(item_id);
+    while let Some(event) = events.next().await {
+        let changed = match event {
+            Event::Put { path, data } if path == "/" => {
+                let item = serde_json::from_value(data).ok();
+                item.is_none() || item != client.cached_item(item_id).await
+            }
+            Event::Put { .. } | Event::Patch { .. } => true,
+            Event::KeepAlive | Event::Cancel => false,
+        };
+        if !changed {
+            continue;
+        }
+
+        client.invalidate_item(item_id).await;
+        let updates = AppData::Updates {
+            items: vec![item_id],
+            stories: Vec::new(),
+        };
+        if tx_data.send(updates).is_err() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hackernews::transport::FixtureTransport;
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    #[tokio::test]
+    async fn test_runs_actions_until_hung_up() {
+        let transport = Arc::new(FixtureTransport::new().with("showstories", json!([3, 1, 2])));
+        let client = HackerNewsClient::builder()
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+        let (tx_action, rx_action) = unbounded_channel();
+        let (tx_data, mut rx_data) = unbounded_channel();
+        let (tx_error, mut rx_error) = unbounded_channel();
+        let worker = tokio::spawn(
+            Worker::new(client, SearchClient::default(), tx_data, tx_error).run(rx_action),
+        );
+
+        tx_action.send(AppAction::Story(StoryType::Show)).unwrap();
+        assert_eq!(
+            rx_data.recv().await,
+            Some(AppData::Story(Some(vec![3, 1, 2])))
+        );
+
+        // A reload skips the cached feed.
+        transport.insert("showstories", json!([4, 3]));
+        tx_action
+            .send(AppAction::Reload(Box::new(AppAction::Story(
+                StoryType::Show,
+            ))))
+            .unwrap();
+        assert_eq!(rx_data.recv().await, Some(AppData::Story(Some(vec![4, 3]))));
+
+        // A failure is reported with the action to retry.
+        tx_action.send(AppAction::User("pg".to_string())).unwrap();
+        let error = rx_error.recv().await.unwrap();
+        assert_eq!(error.retry, Some(AppAction::User("pg".to_string())));
+
+        // Hanging up stops the worker and everything it started.
+        drop(tx_action);
+        worker.await.unwrap();
+        assert_eq!(rx_data.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_watch_stops_when_the_story_is_closed() {
+        let client = HackerNewsClient::builder()
+            .transport(FixtureTransport::new())
+            .build()
+            .unwrap();
+        let (tx_data, _rx_data) = unbounded_channel();
+        let (tx_error, _rx_error) = unbounded_channel();
+        let mut worker = Worker::new(client, SearchClient::default(), tx_data, tx_error);
+
+        worker.handle(AppAction::Watch(Some(1)));
+        assert_eq!(worker.watched, Some(1));
+        assert!(worker.watch.0.is_some());
+
+        worker.handle(AppAction::Watch(None));
+        assert_eq!(worker.watched, None);
+        assert!(worker.watch.0.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_comment_threads_load_independently() {
+        let comment = |id: usize| json!({"id": id, "type": "comment", "time": 0});
+        let transport = FixtureTransport::new()
+            .with("item/1", comment(1))
+            .with("item/2", json!("not an item"))
+            .with("item/3", comment(3));
+        let client = HackerNewsClient::builder()
+            .transport(transport)
+            .build()
+            .unwrap();
+        let (tx_action, rx_action) = unbounded_channel();
+        let (tx_data, mut rx_data) = unbounded_channel();
+        let (tx_error, mut rx_error) = unbounded_channel();
+        tokio::spawn(
+            Worker::new(client, SearchClient::default(), tx_data, tx_error).run(rx_action),
+        );
+
+        tx_action.send(AppAction::Items(vec![1, 2, 3])).unwrap();
+        let mut loaded = Vec::new();
+        for _ in 0..2 {
+            match rx_data.recv().await {
+                Some(AppData::Comment(Some(item))) => loaded.push(item.id),
+                data => panic!("unexpected {:?}", data),
+            }
+        }
+        loaded.sort();
+        assert_eq!(loaded, vec![1, 3]);
+
+        // Only the thread that failed is retried.
+        let error = rx_error.recv().await.unwrap();
+        assert_eq!(error.retry, Some(AppAction::Items(vec![2])));
+        assert_eq!(rx_data.recv().await, Some(AppData::CommentsLoaded));
+    }
+}